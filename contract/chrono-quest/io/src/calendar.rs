@@ -0,0 +1,158 @@
+//! Calendar arithmetic on timestamps seen from a fixed offset from UTC.
+//!
+//! Timestamps are seconds since the Unix epoch and offsets are minutes east of
+//! UTC. Dates follow the proleptic Gregorian calendar and days are counted from
+//! 1970-01-01, so local times before the epoch give negative days.
+
+use gstd::prelude::*;
+use scale_info::TypeInfo;
+
+pub const SECONDS_PER_DAY: i64 = 86400;
+
+#[derive(PartialEq, Eq, PartialOrd, Ord, Debug, Encode, Decode, TypeInfo, Clone, Copy)]
+pub enum Weekday {
+    Monday,
+    Tuesday,
+    Wednesday,
+    Thursday,
+    Friday,
+    Saturday,
+    Sunday,
+}
+
+impl Weekday {
+    const ALL: [Weekday; 7] = [
+        Weekday::Monday,
+        Weekday::Tuesday,
+        Weekday::Wednesday,
+        Weekday::Thursday,
+        Weekday::Friday,
+        Weekday::Saturday,
+        Weekday::Sunday,
+    ];
+
+    /// Weekday of a day counted from 1970-01-01, which was a Thursday.
+    pub fn from_day(day: i64) -> Self {
+        Self::ALL[(day + 3).rem_euclid(7) as usize]
+    }
+
+    /// Number of days since the last Monday.
+    pub fn index(self) -> u8 {
+        self as u8
+    }
+}
+
+#[derive(PartialEq, Eq, PartialOrd, Ord, Debug, Encode, Decode, TypeInfo, Clone, Copy)]
+pub struct CivilDate {
+    pub year: i32,
+    pub month: u8,
+    pub day: u8,
+}
+
+impl CivilDate {
+    pub fn new(year: i32, month: u8, day: u8) -> Option<Self> {
+        if !(1..=12).contains(&month) || day == 0 || day > days_in_month(year, month) {
+            return None;
+        }
+        Some(Self { year, month, day })
+    }
+}
+
+/// Days a challenge window has to start and end on, in its timezone.
+#[derive(PartialEq, Debug, Encode, Decode, TypeInfo, Clone, Copy)]
+pub struct WeekdayWindow {
+    pub first: Weekday,
+    pub last: Weekday,
+}
+
+pub fn is_leap_year(year: i32) -> bool {
+    year % 4 == 0 && (year % 100 != 0 || year % 400 == 0)
+}
+
+pub fn days_in_month(year: i32, month: u8) -> u8 {
+    match month {
+        2 if is_leap_year(year) => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+/// Number of days from 1970-01-01 to `date`.
+pub fn days_from_civil(date: CivilDate) -> i64 {
+    // years start in March so that the leap day is the last day of a year
+    let month = date.month as i64;
+    let year = date.year as i64 - (month <= 2) as i64;
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let day_of_year = (153 * ((month + 9) % 12) + 2) / 5 + date.day as i64 - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146097 + day_of_era - 719468
+}
+
+/// Date of the day `day` days after 1970-01-01.
+pub fn civil_from_days(day: i64) -> CivilDate {
+    let days = day + 719468;
+    let era = days.div_euclid(146097);
+    let day_of_era = days - era * 146097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let shifted_month = (5 * day_of_year + 2) / 153;
+    let month = (shifted_month + 2) % 12 + 1;
+    CivilDate {
+        year: (year_of_era + era * 400 + (month <= 2) as i64) as i32,
+        month: month as u8,
+        day: (day_of_year - (153 * shifted_month + 2) / 5 + 1) as u8,
+    }
+}
+
+/// Local day, counted from 1970-01-01, that `timestamp` falls on.
+pub fn local_day(timestamp: u64, offset: i16) -> i64 {
+    (timestamp as i64 + offset as i64 * 60).div_euclid(SECONDS_PER_DAY)
+}
+
+/// Timestamp of the local midnight starting `day`.
+pub fn local_midnight(day: i64, offset: i16) -> i64 {
+    day * SECONDS_PER_DAY - offset as i64 * 60
+}
+
+pub fn is_local_midnight(timestamp: u64, offset: i16) -> bool {
+    (timestamp as i64 + offset as i64 * 60).rem_euclid(SECONDS_PER_DAY) == 0
+}
+
+pub fn date(timestamp: u64, offset: i16) -> CivilDate {
+    civil_from_days(local_day(timestamp, offset))
+}
+
+pub fn weekday(timestamp: u64, offset: i16) -> Weekday {
+    Weekday::from_day(local_day(timestamp, offset))
+}
+
+/// Number of local midnights from `from` to `to`, negative when `to` is earlier.
+pub fn days_between(from: u64, to: u64, offset: i16) -> i64 {
+    local_day(to, offset) - local_day(from, offset)
+}
+
+/// The same day `months` months later, or earlier for a negative count. Days
+/// past the end of a shorter month move back to its last day.
+pub fn add_months(date: CivilDate, months: i32) -> CivilDate {
+    let month_index = date.year as i64 * 12 + date.month as i64 - 1 + months as i64;
+    let year = month_index.div_euclid(12) as i32;
+    let month = (month_index.rem_euclid(12) + 1) as u8;
+    CivilDate {
+        year,
+        month,
+        day: date.day.min(days_in_month(year, month)),
+    }
+}
+
+/// Whether the window from `start` to `end` covers whole local days, from a
+/// `window.first` up to and including a `window.last`.
+pub fn spans_weekdays(start: u64, end: u64, offset: i16, window: WeekdayWindow) -> bool {
+    start < end
+        && is_local_midnight(start, offset)
+        && is_local_midnight(end, offset)
+        && weekday(start, offset) == window.first
+        && Weekday::from_day(local_day(end, offset) - 1) == window.last
+}
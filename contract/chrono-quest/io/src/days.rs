@@ -0,0 +1,143 @@
+//! Compact set of day numbers, such as the days a participant checked in on.
+//!
+//! Days are stored one bit each in 64-day words, so a year of check-ins takes
+//! six words however many of the days are set.
+
+use gstd::prelude::*;
+use parity_scale_codec::{Error, Input};
+use scale_info::TypeInfo;
+
+const WORD_BITS: u32 = u64::BITS;
+
+#[derive(PartialEq, Eq, Debug, Encode, TypeInfo, Clone, Default)]
+pub struct DayBitset {
+    /// Bit `day % 64` of word `day / 64` is set for each day in the set. The
+    /// last word is never zero.
+    words: Vec<u64>,
+}
+
+/// Rejects encodings ending in a zero word, which would break `last` and make
+/// equal sets compare unequal.
+impl Decode for DayBitset {
+    fn decode<I: Input>(input: &mut I) -> Result<Self, Error> {
+        let words = Vec::<u64>::decode(input)?;
+        if words.last() == Some(&0) {
+            return Err("DayBitset ends in a zero word".into());
+        }
+        Ok(Self { words })
+    }
+}
+
+impl DayBitset {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a day, returning whether it was missing.
+    pub fn insert(&mut self, day: u16) -> bool {
+        let (word, bit) = position(day);
+        if word >= self.words.len() {
+            self.words.resize(word + 1, 0);
+        }
+        let missing = self.words[word] & bit == 0;
+        self.words[word] |= bit;
+        missing
+    }
+
+    pub fn contains(&self, day: u16) -> bool {
+        let (word, bit) = position(day);
+        self.words.get(word).is_some_and(|word| word & bit != 0)
+    }
+
+    /// Number of days in the set.
+    pub fn len(&self) -> u32 {
+        self.words.iter().map(|word| word.count_ones()).sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.words.is_empty()
+    }
+
+    pub fn first(&self) -> Option<u16> {
+        self.iter().next()
+    }
+
+    pub fn last(&self) -> Option<u16> {
+        let word = self.words.last()?;
+        let index = self.words.len() as u32 - 1;
+        Some((index * WORD_BITS + WORD_BITS - 1 - word.leading_zeros()) as u16)
+    }
+
+    /// Days in ascending order.
+    pub fn iter(&self) -> impl Iterator<Item = u16> + '_ {
+        self.words.iter().enumerate().flat_map(|(index, word)| {
+            let mut word = *word;
+            iter::from_fn(move || {
+                if word == 0 {
+                    return None;
+                }
+                let bit = word.trailing_zeros();
+                word &= word - 1;
+                Some((index as u32 * WORD_BITS + bit) as u16)
+            })
+        })
+    }
+
+    /// Length of the run of consecutive days ending at `day`, which is 0 when
+    /// `day` itself is missing.
+    pub fn current_streak(&self, day: u16) -> u16 {
+        let mut streak = 0;
+        let mut day = Some(day);
+        while let Some(current) = day.filter(|day| self.contains(*day)) {
+            streak += 1;
+            day = current.checked_sub(1);
+        }
+        streak
+    }
+
+    /// Length of the longest run of consecutive days.
+    pub fn longest_streak(&self) -> u16 {
+        let (mut longest, mut current) = (0, 0);
+        let mut previous = None;
+        for day in self.iter() {
+            current = match previous {
+                Some(previous) if previous + 1 == day => current + 1,
+                _ => 1,
+            };
+            longest = longest.max(current);
+            previous = Some(day);
+        }
+        longest
+    }
+
+    /// Whether each of the `count` days from `first` is in the set, for drawing
+    /// a row of a calendar.
+    pub fn window(&self, first: u16, count: u16) -> Vec<bool> {
+        (0..count)
+            .map(|offset| {
+                first
+                    .checked_add(offset)
+                    .is_some_and(|day| self.contains(day))
+            })
+            .collect()
+    }
+
+    pub fn to_vec(&self) -> Vec<u16> {
+        self.iter().collect()
+    }
+}
+
+impl FromIterator<u16> for DayBitset {
+    fn from_iter<I: IntoIterator<Item = u16>>(days: I) -> Self {
+        let mut set = Self::new();
+        for day in days {
+            set.insert(day);
+        }
+        set
+    }
+}
+
+fn position(day: u16) -> (usize, u64) {
+    let day = day as u32;
+    ((day / WORD_BITS) as usize, 1 << (day % WORD_BITS))
+}
@@ -1,8 +1,17 @@
 #![no_std]
 
+pub mod calendar;
+pub mod days;
+
+use calendar::{Weekday, WeekdayWindow};
+use days::DayBitset;
 use gmeta::{InOut, Metadata};
-use gstd::{prelude::*, string::String, ActorId};
-use scale_info::TypeInfo;
+use gstd::{collections::BTreeMap, prelude::*, string::String, ActorId};
+use parity_scale_codec::{Input, Output};
+use scale_info::{
+    build::{Fields, Variants},
+    Path, Type, TypeInfo,
+};
 
 pub struct ChronoQuestMetadata;
 
@@ -20,15 +29,34 @@ pub enum Command {
     CreateChallenge {
         name: String,
         entry_fee: u128,
-        timezone: i8,
+        timezone: UtcOffset,
         start_time: u64,
         end_time: u64,
+        cadence: Cadence,
+        /// Tasks a participant has to complete for a day to count.
+        tasks_per_day: u8,
+        /// Weekdays the challenge has to start and end on, if any.
+        window: Option<WeekdayWindow>,
+        /// Whether people may still join once the challenge has started.
+        late_join: Option<LateJoin>,
+        /// Fungible token program the challenge is funded in, or `None` for native value.
+        currency: Option<ActorId>,
+        requirements: EntryRequirements,
+        /// Teams competing against each other; empty for a challenge between individuals.
+        teams: Vec<TeamConfig>,
     },
     JoinChallenge {
         id: u64,
+        /// Index of the team to join, required in team challenges.
+        team: Option<u32>,
+        /// Offset the participant's days follow, the challenge timezone if not set.
+        timezone: Option<UtcOffset>,
     },
     SponsorChallenge {
         id: u64,
+        /// Amount pulled from the sponsor in token challenges; native challenges use the
+        /// attached value instead.
+        amount: u128,
     },
     ChallengeRecruitmentEnded {
         id: u64,
@@ -39,6 +67,41 @@ pub enum Command {
     CompleteDaily {
         id: u64,
     },
+    ClaimBalance {
+        currency: Option<ActorId>,
+    },
+    CreateSeries {
+        /// Name of the spawned challenges, with `{n}` replaced by the edition number.
+        name_pattern: String,
+        entry_fee: u128,
+        timezone: UtcOffset,
+        /// Start of the first edition; the following ones start `cadence_days` apart.
+        first_start_time: u64,
+        cadence_days: u32,
+        duration_days: u32,
+        /// Number of editions after which the series stops, or `None` to run forever.
+        max_editions: Option<u32>,
+        /// Share of the forfeited stakes of each edition kept back to seed the prize
+        /// pool of the next one, in basis points.
+        rollover_bps: u16,
+    },
+    SpawnSeriesChallenge {
+        id: u64,
+    },
+    /// Deposits the attached value and enrolls the sender into every following
+    /// edition of the series for as long as the deposit covers the entry fee.
+    EnrollInSeries {
+        id: u64,
+    },
+    /// Stops auto-enrollment and refunds the remaining deposit.
+    LeaveSeries {
+        id: u64,
+    },
+    /// Archives up to `count` challenges whose retention period after finishing
+    /// is over. Anyone may send it.
+    ArchiveChallenges {
+        count: u32,
+    },
 }
 
 #[derive(TypeInfo, Encode, Decode, Debug)]
@@ -49,9 +112,15 @@ pub enum Event {
     ChallengeJoined {
         id: u64,
     },
+    ChallengeJoinPending {
+        id: u64,
+    },
     ChallengeSponsored {
         id: u64,
     },
+    ChallengeSponsorshipPending {
+        id: u64,
+    },
     ChallengeRecruitmentEnded {
         id: u64,
         status: ChallengeStatus,
@@ -67,21 +136,114 @@ pub enum Event {
     ChallengeDailyCompleted {
         id: u64,
     },
+    /// A task was completed on a day whose quota is not met yet.
+    ChallengeTaskCompleted {
+        id: u64,
+        day: u16,
+        tasks: u8,
+    },
+    ChallengeFinalizationProgressed {
+        id: u64,
+        phase: FinalizationPhase,
+        cursor: u64,
+    },
+    ChallengeRefund {
+        id: u64,
+        account: ActorId,
+        refund: u128,
+    },
+    BalanceClaimed {
+        amount: u128,
+    },
+    SeriesCreated {
+        id: u64,
+        challenge_id: u64,
+    },
+    SeriesChallengeSpawned {
+        id: u64,
+        challenge_id: u64,
+    },
+    SeriesEnrolled {
+        id: u64,
+        balance: u128,
+    },
+    SeriesLeft {
+        id: u64,
+        refund: u128,
+    },
+    ChallengesArchived {
+        ids: Vec<u64>,
+    },
+    /// An entry was appended to the log of a challenge. Broadcast to the zero
+    /// address rather than replied, so that indexers can follow every log.
+    ChallengeLogged {
+        id: u64,
+        entry: LogEntry,
+    },
 }
 
-#[derive(TypeInfo, Decode, Encode, Debug)]
+/// Condition a challenge must meet to be listed by `QueryChallenges`.
+#[derive(TypeInfo, Decode, Encode, Debug, Clone)]
 pub enum ChallengeFilter {
     All,
     Created(ActorId),
     Joined(ActorId),
     Sponsored(ActorId),
+    /// Names containing the text, ignoring case.
+    NameContains(String),
+    /// Entry fees within the range, bounds included.
+    EntryFee {
+        min: u128,
+        max: u128,
+    },
+    /// Start times within the range, bounds included.
+    StartTime {
+        from: u64,
+        to: u64,
+    },
+    /// Offset from UTC in minutes.
+    Timezone(i16),
+    /// Whole days from start to end within the range, bounds included.
+    Duration {
+        min_days: u32,
+        max_days: u32,
+    },
+    /// Challenges that can still be joined, with room left in their teams.
+    Open,
+}
+
+#[derive(TypeInfo, Decode, Encode, Debug, Clone, Copy)]
+pub enum ChallengeSort {
+    Id,
+    PrizePool,
+    StartTime,
+    ParticipantCount,
+}
+
+#[derive(TypeInfo, Decode, Encode, Debug, Clone, Copy)]
+pub enum SortOrder {
+    Ascending,
+    Descending,
 }
 
 #[derive(TypeInfo, Decode, Encode, Debug, Clone)]
 pub struct Participant {
     pub id: ActorId,
     pub payment: u128,
-    pub completed_days: Vec<u16>,
+    /// Days on which the daily quota of tasks was met.
+    pub completed_days: DayBitset,
+    /// Tasks completed on each day whose quota is not met. A day moves over to
+    /// `completed_days` once it is, so its count is not kept twice.
+    pub day_progress: BTreeMap<u16, u8>,
+    pub team: Option<u32>,
+    /// Offset from UTC in minutes that the participant's days follow.
+    pub timezone: i16,
+    /// First local midnight of the participant at or after the challenge start,
+    /// or after joining for a late joiner. Their days are counted from it.
+    pub start_time: u64,
+    /// End of the participant's days: as many days after `start_time` as the
+    /// challenge lasts, or the end of their last day for a late joiner.
+    pub end_time: u64,
 }
 
 #[derive(TypeInfo, Decode, Encode, Debug, Clone)]
@@ -96,25 +258,550 @@ pub struct Challenge {
     pub name: String,
     pub creator: ActorId,
     pub entry_fee: u128,
+    pub currency: Option<ActorId>,
+    pub requirements: EntryRequirements,
 
-    pub timezone: i8,
+    /// Offset from UTC in minutes.
+    pub timezone: i16,
     pub creation_time: u64,
     pub start_time: u64,
     pub end_time: u64,
+    /// End of the last participant's days, when finalization starts.
+    pub execution_end_time: u64,
+    pub cadence: Cadence,
+    pub tasks_per_day: u8,
+    pub late_join: Option<LateJoin>,
 
+    /// Stakes and sponsorships shared among the winners.
     pub prize_pool: u128,
+    /// Part of `prize_pool` carried over from earlier editions of the series.
+    pub carried_over: u128,
+    /// Share of the forfeited stakes kept back for the next edition of the series,
+    /// in basis points.
+    pub rollover_bps: u16,
     pub status: ChallengeStatus,
 
     pub participants: Vec<Participant>,
+    /// Position of each participant in `participants`.
+    pub participant_index: BTreeMap<ActorId, u32>,
     pub sponsors: Vec<Sponsorship>,
+    pub teams: Vec<Team>,
+    /// Series the challenge was spawned by.
+    pub series: Option<u64>,
+
+    pub finalization: Option<Finalization>,
 }
 
-#[derive(PartialEq, Debug, Encode, TypeInfo, Decode, Clone, Copy)]
+impl Challenge {
+    pub fn participant(&self, account: &ActorId) -> Option<&Participant> {
+        self.participant_index
+            .get(account)
+            .map(|index| &self.participants[*index as usize])
+    }
+
+    pub fn summary(&self) -> ChallengeSummary {
+        ChallengeSummary {
+            id: self.id,
+            name: self.name.clone(),
+            creator: self.creator,
+            entry_fee: self.entry_fee,
+            currency: self.currency,
+            timezone: self.timezone,
+            start_time: self.start_time,
+            end_time: self.end_time,
+            status: self.status,
+            prize_pool: self.prize_pool,
+            carried_over: self.carried_over,
+            participant_count: self.participants.len() as u32,
+            sponsor_count: self.sponsors.len() as u32,
+            series: self.series,
+        }
+    }
+
+    /// Amount the sponsors put into the prize pool.
+    pub fn sponsored(&self) -> u128 {
+        self.sponsors
+            .iter()
+            .map(|sponsorship| sponsorship.payment)
+            .sum()
+    }
+
+    pub fn details(&self) -> ChallengeDetails {
+        ChallengeDetails {
+            id: self.id,
+            name: self.name.clone(),
+            creator: self.creator,
+            entry_fee: self.entry_fee,
+            currency: self.currency,
+            requirements: self.requirements.clone(),
+            timezone: self.timezone,
+            creation_time: self.creation_time,
+            start_time: self.start_time,
+            end_time: self.end_time,
+            execution_end_time: self.execution_end_time,
+            cadence: self.cadence,
+            tasks_per_day: self.tasks_per_day,
+            late_join: self.late_join,
+            prize_pool: self.prize_pool,
+            carried_over: self.carried_over,
+            rollover_bps: self.rollover_bps,
+            status: self.status,
+            participant_count: self.participants.len() as u32,
+            sponsor_count: self.sponsors.len() as u32,
+            teams: self.teams.clone(),
+            series: self.series,
+            finalization: self.finalization.clone(),
+        }
+    }
+}
+
+/// A challenge without its participants and sponsors, which are paged through
+/// `QueryParticipants` and `QuerySponsors`.
+#[derive(TypeInfo, Decode, Encode, Debug, Clone)]
+pub struct ChallengeDetails {
+    pub id: u64,
+    pub name: String,
+    pub creator: ActorId,
+    pub entry_fee: u128,
+    pub currency: Option<ActorId>,
+    pub requirements: EntryRequirements,
+
+    /// Offset from UTC in minutes.
+    pub timezone: i16,
+    pub creation_time: u64,
+    pub start_time: u64,
+    pub end_time: u64,
+    /// End of the last participant's days, when finalization starts.
+    pub execution_end_time: u64,
+    pub cadence: Cadence,
+    pub tasks_per_day: u8,
+    pub late_join: Option<LateJoin>,
+
+    /// Stakes and sponsorships shared among the winners.
+    pub prize_pool: u128,
+    /// Part of `prize_pool` carried over from earlier editions of the series.
+    pub carried_over: u128,
+    /// Share of the forfeited stakes kept back for the next edition of the series,
+    /// in basis points.
+    pub rollover_bps: u16,
+    pub status: ChallengeStatus,
+
+    pub participant_count: u32,
+    pub sponsor_count: u32,
+    pub teams: Vec<Team>,
+    /// Series the challenge was spawned by.
+    pub series: Option<u64>,
+
+    pub finalization: Option<Finalization>,
+}
+
+/// What is kept of a finished challenge once its participants and sponsors are
+/// dropped. Claimable balances and payouts are kept by the program apart from
+/// the challenge, so they are not affected.
+#[derive(TypeInfo, Decode, Encode, Debug, Clone)]
+pub struct ArchivedChallenge {
+    pub summary: ChallengeSummary,
+    /// Participants who shared the prize pool.
+    pub winner_count: u64,
+    /// Prizes, or refunds of the prize pool when nobody won it, paid out, or the
+    /// sponsors' refunds when recruitment failed.
+    pub total_paid: u128,
+    pub rolled_over: u128,
+    /// BLAKE2b-256 hash of the SCALE-encoded challenge as it was archived,
+    /// followed by its log, which is dropped.
+    pub record_hash: [u8; 32],
+    pub archived_at: u64,
+}
+
+/// Fixed-size view of a challenge returned by the list queries, leaving out the
+/// participants and sponsors, which are paged through separately.
+#[derive(TypeInfo, Decode, Encode, Debug, Clone)]
+pub struct ChallengeSummary {
+    pub id: u64,
+    pub name: String,
+    pub creator: ActorId,
+    pub entry_fee: u128,
+    pub currency: Option<ActorId>,
+    /// Offset from UTC in minutes.
+    pub timezone: i16,
+    pub start_time: u64,
+    pub end_time: u64,
+    pub status: ChallengeStatus,
+    pub prize_pool: u128,
+    pub carried_over: u128,
+    pub participant_count: u32,
+    pub sponsor_count: u32,
+    pub series: Option<u64>,
+}
+
+/// Offset from UTC, either in whole hours or in minutes for the zones that are
+/// not a whole number of hours away from UTC.
+///
+/// `Hours(h)` is encoded as the bare `i8` hour offset commands used to carry, so
+/// those keep decoding. `Minutes(m)` is encoded as [`UtcOffset::MINUTES_TAG`],
+/// which is no valid hour offset, followed by `m`.
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub enum UtcOffset {
+    Hours(i8),
+    Minutes(i16),
+}
+
+impl UtcOffset {
+    pub const MINUTES_TAG: u8 = i8::MIN as u8;
+}
+
+impl Encode for UtcOffset {
+    fn encode_to<T: Output + ?Sized>(&self, dest: &mut T) {
+        match self {
+            Self::Hours(hours) => hours.encode_to(dest),
+            Self::Minutes(minutes) => {
+                dest.push_byte(Self::MINUTES_TAG);
+                minutes.encode_to(dest);
+            }
+        }
+    }
+}
+
+impl Decode for UtcOffset {
+    fn decode<I: Input>(input: &mut I) -> Result<Self, parity_scale_codec::Error> {
+        match input.read_byte()? {
+            Self::MINUTES_TAG => Ok(Self::Minutes(i16::decode(input)?)),
+            hours => Ok(Self::Hours(hours as i8)),
+        }
+    }
+}
+
+/// Only describes `Minutes`, as an enum variant indexed by the tag, which is
+/// what new clients should send. Bare hour offsets are still accepted.
+impl TypeInfo for UtcOffset {
+    type Identity = Self;
+
+    fn type_info() -> Type {
+        Type::builder()
+            .path(Path::new("UtcOffset", module_path!()))
+            .variant(Variants::new().variant("Minutes", |variant| {
+                variant
+                    .index(Self::MINUTES_TAG)
+                    .fields(Fields::unnamed().field(|field| field.ty::<i16>().type_name("i16")))
+            }))
+    }
+}
+
+impl UtcOffset {
+    pub fn minutes(self) -> i16 {
+        match self {
+            Self::Hours(hours) => hours as i16 * 60,
+            Self::Minutes(minutes) => minutes,
+        }
+    }
+}
+
+/// How often participants have to check in. Days are counted from the start of
+/// the challenge and a participant checks in at most once per day.
+#[derive(PartialEq, Debug, Encode, TypeInfo, Decode, Clone, Copy, Default)]
+pub enum Cadence {
+    #[default]
+    Daily,
+    /// The given number of days in each week, or every day of a shorter last week.
+    TimesPerWeek(u8),
+    /// Monday to Friday in the challenge timezone. A participant in another
+    /// timezone checks in on their own local days, each taking the weekday of
+    /// the challenge day it starts in.
+    Weekdays,
+    /// Once in each period of the given number of days.
+    EveryKDays(u16),
+}
+
+/// Track record an account needs to join a challenge, checked against the
+/// history the program keeps for it. Unset fields are not checked.
+#[derive(TypeInfo, Decode, Encode, Debug, Clone, Default)]
+pub struct EntryRequirements {
+    pub min_completed_battles: Option<u32>,
+    /// Minimum share of finished battles that were completed, in basis points.
+    pub min_completion_rate: Option<u16>,
+    /// Id of an earlier challenge whose completion badge the account must hold.
+    pub required_badge: Option<u64>,
+}
+
+/// Late entry into a challenge that has already started. A late joiner only has
+/// to check in on the days left and shares the prize pool with a weight of
+/// `payment * days_left / challenge_days`, where `days_left` counts whole local
+/// days from the first midnight after joining.
+#[derive(TypeInfo, Decode, Encode, Debug, Clone, Copy, PartialEq)]
+pub struct LateJoin {
+    /// Number of days after the start during which joining stays open.
+    pub days: u32,
+    /// Paid on top of the entry fee by a late joiner.
+    pub premium: u128,
+}
+
+/// Template the program spawns a new challenge from every `cadence_days`.
+#[derive(TypeInfo, Decode, Encode, Debug, Clone)]
+pub struct Series {
+    pub id: u64,
+    pub creator: ActorId,
+    pub name_pattern: String,
+    pub entry_fee: u128,
+    /// Offset from UTC in minutes.
+    pub timezone: i16,
+    pub cadence_days: u32,
+    pub duration_days: u32,
+    pub max_editions: Option<u32>,
+    pub rollover_bps: u16,
+    pub next_start_time: u64,
+    /// Forfeited stakes kept back from finished editions, waiting to seed the next one.
+    pub carry: u128,
+    /// Ids of the spawned challenges, oldest first.
+    pub challenges: Vec<u64>,
+    /// Accounts enrolled automatically into every new edition.
+    pub members: Vec<SeriesMember>,
+}
+
+#[derive(TypeInfo, Decode, Encode, Debug, Clone)]
+pub struct SeriesMember {
+    pub account: ActorId,
+    /// Deposit left to pay the entry fees of the following editions.
+    pub balance: u128,
+}
+
+#[derive(TypeInfo, Decode, Encode, Debug, Clone)]
+pub struct TeamConfig {
+    pub name: String,
+    /// Account that may only join this team.
+    pub captain: Option<ActorId>,
+    pub max_members: Option<u32>,
+}
+
+#[derive(TypeInfo, Decode, Encode, Debug, Clone)]
+pub struct Team {
+    pub name: String,
+    pub captain: Option<ActorId>,
+    pub max_members: Option<u32>,
+    pub members: u32,
+}
+
+/// Results of a team gathered while tallying a team challenge.
+#[derive(TypeInfo, Decode, Encode, Debug, Clone, Default)]
+pub struct TeamTally {
+    pub finished: u32,
+    /// Sum of the members' accuracies, in basis points.
+    pub accuracy_sum: u64,
+    pub total_payment: u128,
+    /// Sum of the members' prize weights, see [`LateJoin`].
+    pub total_weight: u128,
+}
+
+#[derive(PartialEq, Debug, Encode, TypeInfo, Decode, Clone, Copy, Default)]
+pub enum FinalizationPhase {
+    #[default]
+    Tallying,
+    Paying,
+    Done,
+}
+
+/// Progress of a challenge finalization that runs over several messages,
+/// processing at most a bounded batch of participants in each of them.
+#[derive(TypeInfo, Decode, Encode, Debug, Clone, Default)]
+pub struct Finalization {
+    pub phase: FinalizationPhase,
+    /// Index of the next participant to process in the current phase.
+    pub cursor: u64,
+    pub succeeded_count: u64,
+    /// Stake of the participants sharing the prize pool.
+    pub total_succeeded_payment: u128,
+    /// Prize weight of the participants sharing the prize pool, see [`LateJoin`].
+    pub total_succeeded_weight: u128,
+    pub paid_count: u64,
+    pub total_paid: u128,
+    /// Per-team results, indexed like `Challenge::teams`.
+    pub team_tallies: Vec<TeamTally>,
+    /// Team whose members share the prize pool, decided once tallying is over.
+    pub winning_team: Option<u32>,
+    /// Part of the prize pool kept back for the next edition of the series.
+    pub rolled_over: u128,
+}
+
+#[derive(PartialEq, Eq, PartialOrd, Ord, Debug, Encode, TypeInfo, Decode, Clone, Copy)]
 pub enum ChallengeStatus {
     Recruiting,
     RecruitFailed,
     Executing,
+    Finalizing,
+    Completed,
+}
+
+#[derive(PartialEq, Debug, Encode, TypeInfo, Decode, Clone, Copy)]
+pub enum PayoutKind {
+    Prize,
+    Refund,
+    Claim,
+}
+
+#[derive(PartialEq, Debug, Encode, TypeInfo, Decode, Clone, Copy)]
+pub enum PayoutStatus {
+    Pending,
+    Failed,
+}
+
+#[derive(TypeInfo, Decode, Encode, Debug, Clone)]
+pub struct Payout {
+    pub challenge_id: Option<u64>,
+    pub recipient: ActorId,
+    pub amount: u128,
+    pub currency: Option<ActorId>,
+    pub kind: PayoutKind,
+    pub status: PayoutStatus,
+}
+
+/// Value moved through the program in one currency since it was deployed.
+#[derive(TypeInfo, Decode, Encode, Debug, Clone, Default)]
+pub struct CurrencyStats {
+    /// Token program of the currency, or `None` for native value.
+    pub currency: Option<ActorId>,
+    /// Stakes paid by participants, including those paid from series deposits.
+    pub staked: u128,
+    pub sponsored: u128,
+    /// Deposits made into series.
+    pub deposited: u128,
+    /// Prizes sent, less those that bounced back.
+    pub paid_out: u128,
+    /// Refunds sent, less those that bounced back.
+    pub refunded: u128,
+    /// Balances withdrawn with `ClaimBalance`, less those that bounced back.
+    pub claimed: u128,
+    /// Value taken in and not sent out since, which the program holds for
+    /// challenges, series and claimable balances.
+    pub escrow: u128,
+}
+
+/// Platform-wide figures kept up to date by the program.
+#[derive(TypeInfo, Decode, Encode, Debug, Clone)]
+pub struct GlobalStats {
+    /// Totals of every currency used so far.
+    pub currencies: Vec<CurrencyStats>,
+    pub challenge_count: u64,
+    pub recruiting: u64,
+    pub recruit_failed: u64,
+    pub executing: u64,
+    pub finalizing: u64,
+    pub completed: u64,
+    pub archived: u64,
+    /// Accounts that joined at least one challenge.
+    pub unique_participants: u64,
+    /// Days on which a participant met the quota, over all challenges.
+    pub completed_days: u64,
+    /// Native value the program could spend, as reported by the runtime at the
+    /// end of the last message it handled.
+    pub value_available: u128,
+    /// `value_available` less the native escrow: the existential deposit and
+    /// any value sent without being taken in by a command. It is negative if
+    /// the program holds less than its books say.
+    pub unaccounted_value: i128,
+}
+
+/// Entry of the append-only log the program keeps for each challenge.
+#[derive(TypeInfo, Decode, Encode, Debug, Clone)]
+pub struct LogEntry {
+    pub block: u32,
+    pub timestamp: u64,
+    /// Account the entry is about, or the program for the entries it makes on
+    /// its own.
+    pub actor: ActorId,
+    pub action: LogAction,
+    /// Value moved by the action, or 0 when none was.
+    pub amount: u128,
+}
+
+#[derive(PartialEq, Debug, Encode, TypeInfo, Decode, Clone, Copy)]
+pub enum LogAction {
+    /// The amount is the part of the prize pool carried over from the series.
+    Created,
+    Joined,
+    Sponsored,
+    DayCompleted {
+        day: u16,
+    },
+    RecruitmentEnded {
+        status: ChallengeStatus,
+    },
+    PrizeSent,
+    Refunded,
+    /// The amount is the total of the prizes.
     Completed,
+    Archived,
+}
+
+/// Non-transferable badge minted to a participant who completed a challenge.
+#[derive(TypeInfo, Decode, Encode, Debug, Clone)]
+pub struct Badge {
+    pub id: u64,
+    pub owner: ActorId,
+    pub challenge_id: u64,
+    pub challenge_name: String,
+    pub days_completed: u16,
+    /// Share of the challenge days completed, in basis points.
+    pub accuracy: u16,
+    pub minted_at: u64,
+}
+
+/// Aggregate history of an account over all finished challenges.
+#[derive(TypeInfo, Decode, Encode, Debug, Clone, Default)]
+pub struct Profile {
+    pub account: ActorId,
+    pub battles_joined: u32,
+    pub battles_won: u32,
+    pub battles_failed: u32,
+    pub total_days_completed: u32,
+    pub longest_streak: u16,
+    pub lifetime_staked: u128,
+    pub lifetime_won: u128,
+    /// Elo-like score, starting at 1000 and moving with every finished challenge.
+    pub reputation: u32,
+}
+
+/// Progress of a challenge as a whole, worked out by the program.
+#[derive(TypeInfo, Decode, Encode, Debug, Clone)]
+pub struct ChallengeAnalytics {
+    /// Number of participants who met the quota on each day of the challenge,
+    /// with every participant's days counted from their own start.
+    pub daily_completions: Vec<u32>,
+    pub participant_count: u32,
+    /// Participants who have not missed a required check-in so far.
+    pub alive_count: u32,
+    /// Share of the participants still alive, in basis points.
+    pub alive_bps: u16,
+    /// Prize each unit of stake would bring if every participant still alive
+    /// finished, in basis points of the stake.
+    pub projected_prize_per_stake_bps: u128,
+}
+
+/// Prize an account could win by joining a challenge in each of several
+/// scenarios for the other participants, see `StateQuery::SimulatePayout`.
+#[derive(TypeInfo, Decode, Encode, Debug, Clone)]
+pub struct PayoutSimulation {
+    /// Every other participant finishes.
+    pub everyone_finishes: u128,
+    /// The other participants who have not missed a check-in so far finish,
+    /// and the rest make every check-in they still can.
+    pub survivors_finish: u128,
+    /// None of the other participants finishes.
+    pub only_account_finishes: u128,
+}
+
+/// Progress of a participant in a challenge, worked out by the program.
+#[derive(TypeInfo, Decode, Encode, Debug, Clone)]
+pub struct ParticipantProgress {
+    pub account: ActorId,
+    pub days_completed: u16,
+    /// Consecutive days completed up to today, or up to yesterday while today's
+    /// quota is not met yet.
+    pub current_streak: u16,
+    pub longest_streak: u16,
+    /// Whether every required check-in so far was made.
+    pub alive: bool,
+    /// Prize the participant would get if every participant still alive
+    /// finished.
+    pub projected_prize: u128,
 }
 
 #[derive(TypeInfo, Decode, Encode, Debug)]
@@ -125,41 +812,361 @@ pub enum StateQuery {
         id: u64,
     },
 
+    /// Challenges matching every filter and one of the included statuses, with
+    /// ties in the sort kept in id order.
     QueryChallenges {
-        filter: ChallengeFilter,
+        filters: Vec<ChallengeFilter>,
         include_recruiting: bool,
         include_recruit_failed: bool,
         include_executing: bool,
+        include_finalizing: bool,
         include_completed: bool,
+        sort: ChallengeSort,
+        order: SortOrder,
         offset: u64,
         count: u64,
     },
+
+    QueryPayouts {
+        status: PayoutStatus,
+        offset: u64,
+        count: u64,
+    },
+
+    QueryClaimableBalance {
+        account: ActorId,
+        currency: Option<ActorId>,
+    },
+
+    QueryBadge {
+        id: u64,
+    },
+
+    QueryBadges {
+        account: ActorId,
+        offset: u64,
+        count: u64,
+    },
+
+    QueryProfile {
+        account: ActorId,
+    },
+
+    QueryTopProfiles {
+        offset: u64,
+        count: u64,
+    },
+
+    QuerySeries {
+        id: u64,
+    },
+
+    QuerySeriesChallenges {
+        id: u64,
+        offset: u64,
+        count: u64,
+    },
+
+    QueryParticipants {
+        id: u64,
+        offset: u64,
+        count: u64,
+    },
+
+    QueryParticipant {
+        id: u64,
+        account: ActorId,
+    },
+
+    QuerySponsors {
+        id: u64,
+        offset: u64,
+        count: u64,
+    },
+
+    QueryArchivedChallenge {
+        id: u64,
+    },
+
+    /// Archived challenges in id order.
+    QueryArchivedChallenges {
+        offset: u64,
+        count: u64,
+    },
+
+    QueryChallengeAnalytics {
+        id: u64,
+    },
+
+    /// Progress of the participants in the order they joined.
+    QueryParticipantsProgress {
+        id: u64,
+        offset: u64,
+        count: u64,
+    },
+
+    QueryParticipantProgress {
+        id: u64,
+        account: ActorId,
+    },
+
+    /// Prizes `account` could win by joining the challenge now, in `team` for a
+    /// team challenge, with `hypothetical_stake` and making `assumed_completion`
+    /// of their check-ins, in basis points. The challenge is left as it is.
+    SimulatePayout {
+        id: u64,
+        account: ActorId,
+        team: Option<u32>,
+        hypothetical_stake: u128,
+        assumed_completion: u16,
+    },
+
+    /// Log entries of a challenge in the order they were made. The log of an
+    /// archived challenge is only kept as part of its `record_hash`.
+    QueryChallengeLog {
+        id: u64,
+        offset: u64,
+        count: u64,
+    },
+
+    QueryGlobalStats,
 }
 
+#[allow(clippy::large_enum_variant)]
 #[derive(TypeInfo, Encode, Debug, Decode)]
 pub enum StateReply {
-    TotalChallengeCount { count: u64 },
-    QueryChallenge { challenge: Challenge },
-    QueryChallenges { challenges: Vec<Challenge> },
+    TotalChallengeCount {
+        count: u64,
+    },
+    QueryChallenge {
+        challenge: ChallengeDetails,
+    },
+    QueryChallenges {
+        challenges: Vec<ChallengeSummary>,
+        /// Number of matching challenges before paging.
+        total: u64,
+    },
+    QueryPayouts {
+        payouts: Vec<Payout>,
+    },
+    QueryClaimableBalance {
+        amount: u128,
+    },
+    QueryBadge {
+        badge: Badge,
+    },
+    QueryBadges {
+        badges: Vec<Badge>,
+    },
+    QueryProfile {
+        profile: Option<Profile>,
+    },
+    QueryTopProfiles {
+        profiles: Vec<Profile>,
+    },
+    QuerySeries {
+        series: Series,
+    },
+    QuerySeriesChallenges {
+        challenges: Vec<ChallengeSummary>,
+    },
+    QueryParticipants {
+        participants: Vec<Participant>,
+    },
+    QueryParticipant {
+        participant: Option<Participant>,
+    },
+    QuerySponsors {
+        sponsors: Vec<Sponsorship>,
+    },
+    QueryArchivedChallenge {
+        archive: ArchivedChallenge,
+    },
+    QueryArchivedChallenges {
+        archives: Vec<ArchivedChallenge>,
+    },
+    QueryChallengeAnalytics {
+        analytics: ChallengeAnalytics,
+    },
+    QueryParticipantsProgress {
+        progress: Vec<ParticipantProgress>,
+    },
+    QueryParticipantProgress {
+        progress: Option<ParticipantProgress>,
+    },
+    SimulatePayout {
+        simulation: PayoutSimulation,
+    },
+    QueryChallengeLog {
+        entries: Vec<LogEntry>,
+        /// Number of entries in the log before paging.
+        total: u64,
+    },
+    QueryGlobalStats {
+        stats: GlobalStats,
+    },
+    /// The query could not be answered.
+    Error {
+        error: Error,
+    },
 }
 
 #[derive(TypeInfo, Encode, Decode, Debug)]
 pub enum Error {
-    InvalidTimezone { timezone: i8 },
-    InvalidStartTime { time: u64, timezone: i8 },
-    InvalidEndTime { time: u64, timezone: i8 },
-    InvalidTimeRange { start: u64, end: u64 },
-    StartTimeNotInFuture { creation: u64, start: u64 },
-    FailedToScheduleRecruitmentEnd { id: u64 },
-    FailedToScheduleExecutionEnd { id: u64 },
-    ChallengeIsNotRecruiting { id: u64, status: ChallengeStatus },
-    ChallengeIsNotRecruitingAndNotExecuting { id: u64, status: ChallengeStatus },
-    ChallengeIsNotExecuting { id: u64, status: ChallengeStatus },
-    NotEnoughFunds { expected: u128, actual: u128 },
+    InvalidTimezone {
+        timezone: i16,
+    },
+    InvalidStartTime {
+        time: u64,
+        timezone: i16,
+    },
+    InvalidEndTime {
+        time: u64,
+        timezone: i16,
+    },
+    InvalidTimeRange {
+        start: u64,
+        end: u64,
+    },
+    StartTimeNotInFuture {
+        creation: u64,
+        start: u64,
+    },
+    FailedToScheduleRecruitmentEnd {
+        id: u64,
+    },
+    FailedToScheduleExecutionEnd {
+        id: u64,
+    },
+    ChallengeIsNotRecruiting {
+        id: u64,
+        status: ChallengeStatus,
+    },
+    ChallengeIsNotRecruitingAndNotExecuting {
+        id: u64,
+        status: ChallengeStatus,
+    },
+    ChallengeIsNotExecuting {
+        id: u64,
+        status: ChallengeStatus,
+    },
+    NotEnoughFunds {
+        expected: u128,
+        actual: u128,
+    },
     ReplyError,
     SendError,
-    RecruitEndedWithNoParticipants { id: u64 },
+    RecruitEndedWithNoParticipants {
+        id: u64,
+    },
     InternalMethodCalledExternally,
-    ParticipantNotFound { id: u64 },
-    ParticipantAlreadyJoined { id: u64 },
+    ParticipantNotFound {
+        id: u64,
+    },
+    ParticipantAlreadyJoined {
+        id: u64,
+    },
+    ChallengeIsNotExecutingAndNotFinalizing {
+        id: u64,
+        status: ChallengeStatus,
+    },
+    FailedToScheduleFinalizationBatch {
+        id: u64,
+    },
+    NothingToClaim,
+    ValueNotAccepted {
+        value: u128,
+    },
+    TokenTransferFailed {
+        id: u64,
+    },
+    NotEnoughCompletedBattles {
+        required: u32,
+        actual: u32,
+    },
+    CompletionRateTooLow {
+        required: u16,
+        actual: u16,
+    },
+    MissingRequiredBadge {
+        challenge_id: u64,
+    },
+    InvalidTeamCount {
+        count: u32,
+    },
+    TeamRequired {
+        id: u64,
+    },
+    NotATeamChallenge {
+        id: u64,
+    },
+    TeamNotFound {
+        id: u64,
+        team: u32,
+    },
+    TeamIsFull {
+        id: u64,
+        team: u32,
+    },
+    CaptainMustJoinOwnTeam {
+        id: u64,
+        team: u32,
+    },
+    InvalidSeriesCadence {
+        days: u32,
+    },
+    InvalidCadence {
+        cadence: Cadence,
+    },
+    NotACheckInDay {
+        id: u64,
+        day: u16,
+    },
+    CheckInNotOpen {
+        id: u64,
+        opens_at: u64,
+    },
+    WindowNotOnWeekdays {
+        first: Weekday,
+        last: Weekday,
+    },
+    InvalidTasksPerDay {
+        tasks_per_day: u8,
+    },
+    InvalidRolloverRate {
+        bps: u16,
+    },
+    SeriesIsOver {
+        id: u64,
+    },
+    FailedToScheduleSeriesChallenge {
+        id: u64,
+    },
+    NotEnrolledInSeries {
+        id: u64,
+    },
+    /// The late join window is over or no check-in day is left to join for.
+    LateJoinClosed {
+        id: u64,
+    },
+    ChallengeNotFound {
+        id: u64,
+    },
+    /// Only the summary of the challenge is left.
+    ChallengeArchived {
+        id: u64,
+    },
+    SeriesNotFound {
+        id: u64,
+    },
+    BadgeNotFound {
+        id: u64,
+    },
+    /// The payload is not a valid command or query.
+    DecodeFailed,
+    /// The message has too little gas left to handle the reply to a transfer.
+    NotEnoughGasForReply {
+        required: u64,
+        available: u64,
+    },
 }
@@ -0,0 +1,208 @@
+use chrono_quest_io::calendar::{
+    add_months, civil_from_days, date, days_between, days_from_civil, days_in_month, is_leap_year,
+    is_local_midnight, local_day, local_midnight, spans_weekdays, weekday, CivilDate, Weekday,
+    WeekdayWindow, SECONDS_PER_DAY,
+};
+
+// 2024-01-01 00:00:00 UTC, a Monday
+const MONDAY: u64 = 1704067200;
+
+fn civil(year: i32, month: u8, day: u8) -> CivilDate {
+    CivilDate::new(year, month, day).unwrap()
+}
+
+fn next_date(date: CivilDate) -> CivilDate {
+    if date.day < days_in_month(date.year, date.month) {
+        civil(date.year, date.month, date.day + 1)
+    } else if date.month < 12 {
+        civil(date.year, date.month + 1, 1)
+    } else {
+        civil(date.year + 1, 1, 1)
+    }
+}
+
+fn offsets() -> impl Iterator<Item = i16> {
+    (-720..=840).step_by(15)
+}
+
+#[test]
+fn test_days_round_trip_through_every_date() {
+    // walks day by day over roughly 5,500 years, comparing against naive stepping
+    let first_day = -1_000_000;
+    let mut expected = civil_from_days(first_day);
+    for day in first_day..1_000_000 {
+        let date = civil_from_days(day);
+        assert_eq!(date, expected, "day {day}");
+        assert_eq!(days_from_civil(date), day);
+        expected = next_date(date);
+    }
+}
+
+#[test]
+fn test_known_dates() {
+    assert_eq!(days_from_civil(civil(1970, 1, 1)), 0);
+    assert_eq!(civil_from_days(-1), civil(1969, 12, 31));
+    assert_eq!(civil_from_days(19723), civil(2024, 1, 1));
+    assert_eq!(days_from_civil(civil(2000, 3, 1)), 11017);
+    assert_eq!(civil_from_days(-719468), civil(0, 3, 1));
+}
+
+#[test]
+fn test_leap_years() {
+    assert!(is_leap_year(2024));
+    assert!(is_leap_year(2000));
+    assert!(is_leap_year(0));
+    assert!(is_leap_year(-4));
+    assert!(!is_leap_year(1900));
+    assert!(!is_leap_year(2100));
+    assert!(!is_leap_year(2023));
+
+    assert_eq!(days_in_month(2024, 2), 29);
+    assert_eq!(days_in_month(1900, 2), 28);
+    assert!(CivilDate::new(2000, 2, 29).is_some());
+    assert!(CivilDate::new(2100, 2, 29).is_none());
+    assert!(CivilDate::new(2023, 13, 1).is_none());
+    assert!(CivilDate::new(2023, 4, 0).is_none());
+
+    let days_in =
+        |year| days_from_civil(civil(year + 1, 1, 1)) - days_from_civil(civil(year, 1, 1));
+    for year in -1000..3000 {
+        assert_eq!(days_in(year), if is_leap_year(year) { 366 } else { 365 });
+    }
+}
+
+#[test]
+fn test_weekdays() {
+    assert_eq!(Weekday::from_day(0), Weekday::Thursday);
+    assert_eq!(Weekday::from_day(-1), Weekday::Wednesday);
+    assert_eq!(
+        Weekday::from_day(days_from_civil(civil(2000, 1, 1))),
+        Weekday::Saturday
+    );
+    assert_eq!(
+        Weekday::from_day(days_from_civil(civil(1900, 1, 1))),
+        Weekday::Monday
+    );
+    assert_eq!(weekday(MONDAY, 0), Weekday::Monday);
+
+    for day in -100_000..100_000 {
+        let (today, tomorrow) = (Weekday::from_day(day), Weekday::from_day(day + 1));
+        assert_eq!((today.index() + 1) % 7, tomorrow.index());
+    }
+}
+
+#[test]
+fn test_local_days_for_every_offset() {
+    for offset in offsets() {
+        for timestamp in (0..40 * SECONDS_PER_DAY as u64).step_by(997) {
+            let day = local_day(timestamp, offset);
+            let midnight = local_midnight(day, offset);
+            assert!(midnight <= timestamp as i64);
+            assert!(timestamp as i64 - midnight < SECONDS_PER_DAY);
+            assert_eq!(
+                is_local_midnight(timestamp, offset),
+                midnight == timestamp as i64
+            );
+        }
+    }
+}
+
+#[test]
+fn test_local_times_before_the_epoch() {
+    // the epoch is still the last day of 1969 west of UTC
+    assert_eq!(local_day(0, -720), -1);
+    assert_eq!(date(0, -720), civil(1969, 12, 31));
+    assert_eq!(weekday(0, -720), Weekday::Wednesday);
+    assert_eq!(date(3600, -300), civil(1969, 12, 31));
+    assert_eq!(local_midnight(-1, 0), -SECONDS_PER_DAY);
+    assert_eq!(local_midnight(0, -720), 12 * 3600);
+
+    assert!(is_local_midnight(12 * 3600, -720));
+    assert!(!is_local_midnight(0, -60));
+    assert!(is_local_midnight(0, 0));
+    assert!(!is_local_midnight(3600, 0));
+}
+
+#[test]
+fn test_days_between() {
+    assert_eq!(days_between(MONDAY, MONDAY + 3600, 0), 0);
+    assert_eq!(days_between(MONDAY - 1, MONDAY, 0), 1);
+    assert_eq!(days_between(MONDAY, MONDAY - 1, 0), -1);
+    assert_eq!(days_between(MONDAY, MONDAY + 30 * 86400, 330), 30);
+    // 23:00 and 01:00 UTC are on the same local day in UTC+2
+    assert_eq!(days_between(MONDAY - 3600, MONDAY + 3600, 0), 1);
+    assert_eq!(days_between(MONDAY - 3600, MONDAY + 3600, 120), 0);
+
+    for offset in offsets() {
+        for day in 0..60 {
+            let from = local_midnight(19000, offset) as u64;
+            let to = local_midnight(19000 + day, offset) as u64;
+            assert_eq!(days_between(from, to, offset), day);
+            assert_eq!(days_between(from, to + 86399, offset), day);
+        }
+    }
+}
+
+#[test]
+fn test_add_months() {
+    assert_eq!(add_months(civil(2024, 1, 31), 1), civil(2024, 2, 29));
+    assert_eq!(add_months(civil(2023, 1, 31), 1), civil(2023, 2, 28));
+    assert_eq!(add_months(civil(2024, 2, 29), 12), civil(2025, 2, 28));
+    assert_eq!(add_months(civil(2024, 1, 15), -1), civil(2023, 12, 15));
+    assert_eq!(add_months(civil(2024, 3, 31), -1), civil(2024, 2, 29));
+    assert_eq!(add_months(civil(2024, 5, 10), 0), civil(2024, 5, 10));
+    assert_eq!(add_months(civil(0, 1, 1), -1), civil(-1, 12, 1));
+
+    let mut day = days_from_civil(civil(1999, 1, 1));
+    while day < days_from_civil(civil(2003, 1, 1)) {
+        let start = civil_from_days(day);
+        for months in -30..30 {
+            let moved = add_months(start, months);
+            assert!(CivilDate::new(moved.year, moved.month, moved.day).is_some());
+            assert_eq!(
+                moved.year * 12 + moved.month as i32,
+                start.year * 12 + start.month as i32 + months
+            );
+            assert!(moved.day <= start.day);
+
+            let back = add_months(moved, -months);
+            assert_eq!((back.year, back.month), (start.year, start.month));
+            if moved.day == start.day {
+                assert_eq!(back, start);
+            }
+        }
+        day += 1;
+    }
+}
+
+#[test]
+fn test_spans_weekdays() {
+    let work_week = WeekdayWindow {
+        first: Weekday::Monday,
+        last: Weekday::Friday,
+    };
+    let saturday = MONDAY + 5 * 86400;
+    assert!(spans_weekdays(MONDAY, saturday, 0, work_week));
+    assert!(spans_weekdays(MONDAY, saturday + 7 * 86400, 0, work_week));
+    assert!(!spans_weekdays(MONDAY, saturday + 86400, 0, work_week));
+    assert!(!spans_weekdays(MONDAY + 86400, saturday, 0, work_week));
+    assert!(!spans_weekdays(MONDAY + 3600, saturday, 0, work_week));
+    assert!(!spans_weekdays(saturday, MONDAY, 0, work_week));
+
+    // the same week seen from UTC+5:30
+    let shift = 330 * 60;
+    assert!(spans_weekdays(
+        MONDAY - shift,
+        saturday - shift,
+        330,
+        work_week
+    ));
+    assert!(!spans_weekdays(MONDAY, saturday, 330, work_week));
+
+    let single_day = WeekdayWindow {
+        first: Weekday::Sunday,
+        last: Weekday::Sunday,
+    };
+    let sunday = MONDAY - 86400;
+    assert!(spans_weekdays(sunday, MONDAY, 0, single_day));
+}
@@ -0,0 +1,129 @@
+use chrono_quest_io::days::DayBitset;
+use parity_scale_codec::{Decode, Encode};
+use scale_info::TypeInfo;
+use std::collections::BTreeSet;
+
+fn naive_longest_streak(days: &BTreeSet<u16>) -> u16 {
+    let (mut longest, mut current, mut previous) = (0, 0, None);
+    for &day in days {
+        current = if previous.is_some_and(|previous: u16| previous + 1 == day) {
+            current + 1
+        } else {
+            1
+        };
+        longest = longest.max(current);
+        previous = Some(day);
+    }
+    longest
+}
+
+fn naive_current_streak(days: &BTreeSet<u16>, day: u16) -> u16 {
+    (0..=day).rev().take_while(|day| days.contains(day)).count() as u16
+}
+
+#[test]
+fn test_empty_set() {
+    let set = DayBitset::new();
+    assert!(set.is_empty());
+    assert_eq!(set.len(), 0);
+    assert_eq!(set.first(), None);
+    assert_eq!(set.last(), None);
+    assert_eq!(set.iter().next(), None);
+    assert_eq!(set.longest_streak(), 0);
+    assert_eq!(set.current_streak(0), 0);
+    assert_eq!(set.window(0, 3), vec![false; 3]);
+    assert_eq!(set.encode(), vec![0]);
+}
+
+#[test]
+fn test_insert_and_contains() {
+    let mut set = DayBitset::new();
+    assert!(set.insert(3));
+    assert!(!set.insert(3));
+    assert!(set.insert(64));
+    assert!(set.insert(u16::MAX));
+    assert!(set.contains(3) && set.contains(64) && set.contains(u16::MAX));
+    assert!(!set.contains(4) && !set.contains(63) && !set.contains(65));
+    assert_eq!(set.len(), 3);
+    assert_eq!(set.first(), Some(3));
+    assert_eq!(set.last(), Some(u16::MAX));
+    assert_eq!(set.to_vec(), vec![3, 64, u16::MAX]);
+    assert_eq!(set.current_streak(u16::MAX), 1);
+    assert_eq!(set.window(u16::MAX - 1, 3), vec![false, true, false]);
+}
+
+#[test]
+fn test_streaks() {
+    let set: DayBitset = [0, 1, 2, 5, 6, 62, 63, 64, 65, 66].into_iter().collect();
+    assert_eq!(set.longest_streak(), 5);
+    assert_eq!(set.current_streak(2), 3);
+    assert_eq!(set.current_streak(3), 0);
+    assert_eq!(set.current_streak(6), 2);
+    assert_eq!(set.current_streak(64), 3);
+    assert_eq!(set.current_streak(66), 5);
+    assert_eq!(
+        set.window(0, 8),
+        vec![true, true, true, false, false, true, true, false]
+    );
+}
+
+#[test]
+fn test_matches_a_sorted_set() {
+    // pseudo-random days spread over a year, with runs of all lengths
+    let mut seed = 11u64;
+    let mut days = BTreeSet::new();
+    let mut set = DayBitset::new();
+    for _ in 0..400 {
+        seed = seed
+            .wrapping_mul(6364136223846793005)
+            .wrapping_add(1442695040888963407);
+        let day = (seed >> 33) as u16 % 366;
+        assert_eq!(set.insert(day), days.insert(day));
+
+        assert_eq!(set.len(), days.len() as u32);
+        assert_eq!(set.first(), days.first().copied());
+        assert_eq!(set.last(), days.last().copied());
+        assert_eq!(set.longest_streak(), naive_longest_streak(&days));
+    }
+
+    assert_eq!(set.to_vec(), days.iter().copied().collect::<Vec<_>>());
+    for day in 0..400 {
+        assert_eq!(set.contains(day), days.contains(&day));
+        assert_eq!(set.current_streak(day), naive_current_streak(&days, day));
+    }
+    assert_eq!(
+        set.window(300, 100),
+        (300..400)
+            .map(|day| days.contains(&day))
+            .collect::<Vec<_>>()
+    );
+    assert_eq!(days.into_iter().collect::<DayBitset>(), set);
+}
+
+#[test]
+fn test_scale_encoding() {
+    let set: DayBitset = [1, 64, 200].into_iter().collect();
+    let encoded = set.encode();
+    // a compact length of 4 words, then each word in little-endian order
+    assert_eq!(encoded.len(), 1 + 4 * 8);
+    assert_eq!(encoded[..9], [16, 2, 0, 0, 0, 0, 0, 0, 0]);
+    assert_eq!(DayBitset::decode(&mut encoded.as_slice()).unwrap(), set);
+
+    let type_info = DayBitset::type_info();
+    assert_eq!(type_info.path.ident(), Some("DayBitset"));
+}
+
+#[test]
+fn test_decoding_rejects_a_trailing_zero_word() {
+    assert_eq!(
+        DayBitset::decode(&mut [0].as_slice()).unwrap(),
+        DayBitset::new()
+    );
+    for words in [vec![0u64], vec![2, 0], vec![2, 0, 0]] {
+        assert!(DayBitset::decode(&mut words.encode().as_slice()).is_err());
+    }
+    // a zero word is fine anywhere else
+    let set = DayBitset::decode(&mut vec![0u64, 2].encode().as_slice()).unwrap();
+    assert_eq!(set.to_vec(), vec![65]);
+    assert_eq!(set.last(), Some(65));
+}
@@ -0,0 +1,34 @@
+use chrono_quest_io::UtcOffset;
+use parity_scale_codec::{Decode, Encode};
+
+#[test]
+fn test_bare_hour_offsets_decode() {
+    assert_eq!(UtcOffset::decode(&mut &[8][..]), Ok(UtcOffset::Hours(8)));
+    assert_eq!(
+        UtcOffset::decode(&mut &(-5i8).encode()[..]),
+        Ok(UtcOffset::Hours(-5))
+    );
+    // the fields after the offset are left where an `i8` would leave them
+    let payload = (8i8, 1_700_000_000u64).encode();
+    assert_eq!(
+        <(UtcOffset, u64)>::decode(&mut &payload[..]),
+        Ok((UtcOffset::Hours(8), 1_700_000_000))
+    );
+}
+
+#[test]
+fn test_offsets_round_trip() {
+    for offset in [
+        UtcOffset::Hours(-12),
+        UtcOffset::Hours(14),
+        UtcOffset::Minutes(330),
+        UtcOffset::Minutes(-570),
+    ] {
+        assert_eq!(UtcOffset::decode(&mut &offset.encode()[..]), Ok(offset));
+    }
+    assert_eq!(UtcOffset::Hours(8).encode(), vec![8]);
+    assert_eq!(
+        UtcOffset::Minutes(345).encode(),
+        vec![UtcOffset::MINUTES_TAG, 0x59, 0x01]
+    );
+}
@@ -0,0 +1,40 @@
+use blake2::{digest::consts::U32, Blake2b, Digest};
+use chrono_quest_io::{ArchivedChallenge, Challenge, ChallengeStatus, LogEntry};
+use gstd::prelude::*;
+
+/// Time a finished challenge stays in full before it may be archived, in seconds.
+pub const ARCHIVE_RETENTION: u64 = 7 * 86400;
+
+/// When the challenge finished, or `None` while it can still change.
+pub fn finished_at(challenge: &Challenge) -> Option<u64> {
+    match challenge.status {
+        ChallengeStatus::RecruitFailed => Some(challenge.start_time),
+        ChallengeStatus::Completed => Some(challenge.execution_end_time),
+        _ => None,
+    }
+}
+
+pub fn archive(challenge: &Challenge, log: &[LogEntry], archived_at: u64) -> ArchivedChallenge {
+    let (winner_count, total_paid, rolled_over) = match &challenge.finalization {
+        Some(progress) => (
+            progress.paid_count,
+            progress.total_paid,
+            progress.rolled_over,
+        ),
+        None => (0, challenge.sponsored(), 0),
+    };
+
+    ArchivedChallenge {
+        summary: challenge.summary(),
+        winner_count,
+        total_paid,
+        rolled_over,
+        record_hash: record_hash(challenge, log),
+        archived_at,
+    }
+}
+
+/// BLAKE2b-256 hash of the SCALE-encoded challenge and its log.
+pub fn record_hash(challenge: &Challenge, log: &[LogEntry]) -> [u8; 32] {
+    Blake2b::<U32>::digest((challenge, log).encode()).into()
+}
@@ -0,0 +1,48 @@
+use chrono_quest_io::Badge;
+use gstd::{collections::BTreeMap, exec, prelude::*, ActorId};
+
+/// Minimal registry of soulbound completion badges.
+///
+/// Badges are minted at finalization and there is no way to transfer or burn
+/// them, so each one stays with the account that earned it.
+#[derive(Default)]
+pub struct BadgeRegistry {
+    badges: Vec<Badge>,
+    owned: BTreeMap<ActorId, Vec<u64>>,
+}
+
+impl BadgeRegistry {
+    pub fn mint(
+        &mut self,
+        owner: ActorId,
+        challenge_id: u64,
+        challenge_name: String,
+        days_completed: u16,
+        accuracy: u16,
+    ) -> u64 {
+        let id = self.badges.len() as u64;
+        self.badges.push(Badge {
+            id,
+            owner,
+            challenge_id,
+            challenge_name,
+            days_completed,
+            accuracy,
+            minted_at: exec::block_timestamp() / 1000,
+        });
+        self.owned.entry(owner).or_default().push(id);
+        id
+    }
+
+    pub fn get(&self, id: u64) -> Option<&Badge> {
+        self.badges.get(id as usize)
+    }
+
+    pub fn owned_by(&self, owner: &ActorId) -> impl Iterator<Item = &Badge> {
+        self.owned
+            .get(owner)
+            .into_iter()
+            .flatten()
+            .map(|id| &self.badges[*id as usize])
+    }
+}
@@ -1,42 +1,167 @@
-use crate::time::{is_start_of_day, seconds_to_blocks};
+use crate::math::mul_div;
+use crate::time::{
+    day_start_at_or_after, is_start_of_day, is_valid_timezone, seconds_to_blocks, weekday, Schedule,
+};
 use chrono_quest_io::{
-    Challenge, ChallengeStatus, Command, Error, Event, Participant, Sponsorship,
+    calendar::{spans_weekdays, WeekdayWindow},
+    days::DayBitset,
+    Badge, Cadence, Challenge, ChallengeAnalytics, ChallengeFilter, ChallengeStatus, Command,
+    EntryRequirements, Error, Finalization, FinalizationPhase, LateJoin, Participant,
+    ParticipantProgress, PayoutSimulation, Profile, Sponsorship, Team, TeamConfig, TeamTally,
 };
-use gstd::{exec, msg, prelude::*};
+use gstd::{collections::BTreeMap, exec, msg, prelude::*, ActorId};
+
+/// Longest a challenge may run, in days, so that every day fits a `u16`.
+pub const MAX_CHALLENGE_DAYS: u64 = u16::MAX as u64;
+
+/// Maximum number of participants processed by a single finalization message.
+pub const FINALIZATION_BATCH_SIZE: usize = 50;
+
+/// Maximum number of prizes paid by a single finalization message, each of
+/// which takes a reply deposit out of its gas.
+pub const FINALIZATION_PAYOUTS_PER_BATCH: usize = 20;
+
+/// Gas a finalization message keeps for the batches still to come. Prizes it
+/// can't send without going below it are credited to the winners' claimable
+/// balances instead.
+pub const FINALIZATION_GAS_RESERVE: u64 = 100_000_000_000;
+
+/// Settings a challenge is created with.
+pub struct ChallengeConfig {
+    pub name: String,
+    pub entry_fee: u128,
+    /// Offset from UTC in minutes.
+    pub timezone: i16,
+    pub start_time: u64,
+    pub end_time: u64,
+    pub cadence: Cadence,
+    pub tasks_per_day: u8,
+    pub window: Option<WeekdayWindow>,
+    pub late_join: Option<LateJoin>,
+    pub currency: Option<ActorId>,
+    pub requirements: EntryRequirements,
+    pub teams: Vec<TeamConfig>,
+    pub series: Option<u64>,
+    pub rollover_bps: u16,
+}
+
+/// What the program has recorded about an account, checked against the
+/// entry requirements of a challenge.
+pub struct History<'a> {
+    pub profile: Option<&'a Profile>,
+    pub badges: Vec<&'a Badge>,
+}
+
+/// Final result of a participant, with the prize or refund to be paid out by the
+/// caller.
+pub struct Settlement {
+    pub account: ActorId,
+    pub payment: u128,
+    pub succeeded: bool,
+    /// Whether the participant shares the prize pool: a finisher in a challenge
+    /// between individuals, or a member of the winning team.
+    pub winner: bool,
+    pub prize: u128,
+    /// Stake handed back when nobody wins the prize pool, see [`is_refunded`].
+    pub refund: u128,
+    pub days_completed: u16,
+    pub longest_streak: u16,
+    /// Share of the challenge days completed, in basis points.
+    pub accuracy: u16,
+}
 
 pub trait ChallengeOps {
-    fn new(
-        id: u64,
-        name: String,
-        entry_fee: u128,
-        timezone: i8,
-        start_time: u64,
-        end_time: u64,
-    ) -> Result<Self, Error>
+    fn new(id: u64, config: ChallengeConfig) -> Result<Self, Error>
     where
         Self: Sized;
 
-    fn join(&mut self) -> Result<(), Error>;
+    fn can_join(&self, account: ActorId, team: Option<u32>, history: &History)
+        -> Result<(), Error>;
+
+    /// Amount to pay for joining now: the entry fee, plus the late premium once
+    /// the challenge has started.
+    fn join_fee(&self) -> u128;
 
-    fn sponsor(&mut self) -> Result<(), Error>;
+    /// Adds a participant whose days follow `timezone`, in minutes, or the
+    /// challenge timezone when it is not set.
+    fn join(
+        &mut self,
+        account: ActorId,
+        payment: u128,
+        team: Option<u32>,
+        timezone: Option<i16>,
+        history: &History,
+    ) -> Result<(), Error>;
+
+    /// Whether the challenge can still be joined, counting the late join window
+    /// and the room left in its teams.
+    fn is_open(&self) -> bool;
+
+    fn matches(&self, filter: &ChallengeFilter) -> bool;
+
+    fn can_sponsor(&self) -> Result<(), Error>;
+
+    fn sponsor(&mut self, account: ActorId, payment: u128) -> Result<(), Error>;
 
     fn recruitment_ended(&mut self) -> Result<(), Error>;
 
-    fn execution_ended(&mut self) -> Result<(), Error>;
+    fn execution_ended(&mut self) -> Result<Vec<Settlement>, Error>;
+
+    /// Records a completed task for the current day, returning the day, the
+    /// number of tasks done on it so far and whether this task completed it.
+    fn complete_daily(&mut self) -> Result<(u16, u8, bool), Error>;
+
+    /// Progress of the challenge at `now`, in seconds.
+    fn analytics(&self, now: u64) -> ChallengeAnalytics;
 
-    fn complete_daily(&mut self) -> Result<(), Error>;
+    /// Progress at `now` of `count` participants from `offset`, in the order
+    /// they joined.
+    fn participants_progress(
+        &self,
+        now: u64,
+        offset: usize,
+        count: usize,
+    ) -> Vec<ParticipantProgress>;
+
+    fn participant_progress(&self, now: u64, account: &ActorId) -> Option<ParticipantProgress>;
+
+    /// Prizes `account` could win by joining at `now` with `stake` and making
+    /// `completion` of their check-ins, in basis points, worked out on a copy of
+    /// the challenge by the same code that finalizes it.
+    fn simulate_payout(
+        &self,
+        account: ActorId,
+        team: Option<u32>,
+        stake: u128,
+        completion: u16,
+        history: &History,
+        now: u64,
+    ) -> Result<PayoutSimulation, Error>;
 }
 
 impl ChallengeOps for Challenge {
-    fn new(
-        id: u64,
-        name: String,
-        entry_fee: u128,
-        timezone: i8,
-        start_time: u64,
-        end_time: u64,
-    ) -> Result<Self, Error> {
-        if timezone < -12 || timezone > 12 {
+    fn new(id: u64, config: ChallengeConfig) -> Result<Self, Error> {
+        let ChallengeConfig {
+            name,
+            entry_fee,
+            timezone,
+            start_time,
+            end_time,
+            cadence,
+            tasks_per_day,
+            window,
+            late_join,
+            currency,
+            requirements,
+            teams,
+            series,
+            rollover_bps,
+        } = config;
+
+        if teams.len() == 1 {
+            return Err(Error::InvalidTeamCount { count: 1 });
+        }
+        if !is_valid_timezone(timezone) {
             return Err(Error::InvalidTimezone { timezone });
         }
         if !is_start_of_day(start_time, timezone) {
@@ -51,12 +176,33 @@ impl ChallengeOps for Challenge {
                 timezone,
             });
         }
-        if start_time >= end_time {
+        if start_time >= end_time || (end_time - start_time) / 86400 > MAX_CHALLENGE_DAYS {
             return Err(Error::InvalidTimeRange {
                 start: start_time,
                 end: end_time,
             });
         }
+        let valid_cadence = match cadence {
+            Cadence::Daily | Cadence::Weekdays => true,
+            Cadence::TimesPerWeek(times) => (1..=7).contains(&times),
+            Cadence::EveryKDays(days) => days > 0,
+        };
+        if !valid_cadence
+            || Schedule::new(cadence, start_time, end_time, timezone).required_check_ins() == 0
+        {
+            return Err(Error::InvalidCadence { cadence });
+        }
+        if tasks_per_day == 0 {
+            return Err(Error::InvalidTasksPerDay { tasks_per_day });
+        }
+        if let Some(window) = window {
+            if !spans_weekdays(start_time, end_time, timezone, window) {
+                return Err(Error::WindowNotOnWeekdays {
+                    first: window.first,
+                    last: window.last,
+                });
+            }
+        }
 
         let creation_time = exec::block_timestamp() / 1000;
         if creation_time >= start_time {
@@ -79,66 +225,197 @@ impl ChallengeOps for Challenge {
             name,
             creator: msg::source(),
             entry_fee,
+            currency,
+            requirements,
             timezone,
             creation_time,
             start_time,
             end_time,
+            execution_end_time: end_time,
+            cadence,
+            tasks_per_day,
+            late_join,
             prize_pool: 0,
+            carried_over: 0,
+            rollover_bps,
             status: ChallengeStatus::Recruiting,
             participants: Vec::new(),
+            participant_index: BTreeMap::new(),
             sponsors: Vec::new(),
+            teams: teams
+                .into_iter()
+                .map(|team| Team {
+                    name: team.name,
+                    captain: team.captain,
+                    max_members: team.max_members,
+                    members: 0,
+                })
+                .collect(),
+            series,
+            finalization: None,
         })
     }
 
-    fn join(&mut self) -> Result<(), Error> {
-        if self.status != ChallengeStatus::Recruiting {
-            return Err(Error::ChallengeIsNotRecruiting {
-                id: self.id,
-                status: self.status,
-            });
+    fn can_join(
+        &self,
+        account: ActorId,
+        team: Option<u32>,
+        history: &History,
+    ) -> Result<(), Error> {
+        match (self.status, self.late_join) {
+            (ChallengeStatus::Recruiting, _) => {}
+            (ChallengeStatus::Executing, Some(late_join)) => {
+                let now = exec::block_timestamp() / 1000;
+                if now >= self.start_time + late_join.days as u64 * 86400 {
+                    return Err(Error::LateJoinClosed { id: self.id });
+                }
+            }
+            (status, _) => {
+                return Err(Error::ChallengeIsNotRecruiting {
+                    id: self.id,
+                    status,
+                })
+            }
+        }
+        if self.participant_index.contains_key(&account) {
+            return Err(Error::ParticipantAlreadyJoined { id: self.id });
+        }
+
+        check_team(self, account, team)?;
+        check_requirements(&self.requirements, history)
+    }
+
+    fn join_fee(&self) -> u128 {
+        match (self.status, self.late_join) {
+            (ChallengeStatus::Executing, Some(late_join)) => self.entry_fee + late_join.premium,
+            _ => self.entry_fee,
+        }
+    }
+
+    fn join(
+        &mut self,
+        account: ActorId,
+        payment: u128,
+        team: Option<u32>,
+        timezone: Option<i16>,
+        history: &History,
+    ) -> Result<(), Error> {
+        self.can_join(account, team, history)?;
+        let timezone = timezone.unwrap_or(self.timezone);
+        if !is_valid_timezone(timezone) {
+            return Err(Error::InvalidTimezone { timezone });
         }
-        if msg::value() < self.entry_fee {
+        let fee = self.join_fee();
+        if payment < fee {
             return Err(Error::NotEnoughFunds {
-                expected: self.entry_fee,
-                actual: msg::value(),
+                expected: fee,
+                actual: payment,
             });
         }
-        if self
-            .participants
-            .iter()
-            .any(|participant| participant.id == msg::source())
+
+        let start_time = day_start_at_or_after(self.start_time, timezone);
+        let end_time = start_time + (self.end_time - self.start_time);
+        let (start_time, end_time) = if self.status == ChallengeStatus::Executing {
+            // a late joiner starts at their next midnight and cannot outlast the challenge
+            let now = exec::block_timestamp() / 1000;
+            (
+                day_start_at_or_after(now, timezone),
+                end_time.min(self.execution_end_time),
+            )
+        } else {
+            (start_time, end_time)
+        };
+        let participant = Participant {
+            id: account,
+            payment,
+            completed_days: DayBitset::new(),
+            day_progress: BTreeMap::new(),
+            team,
+            timezone,
+            start_time,
+            end_time,
+        };
+        if participant.start_time >= participant.end_time
+            || participant_schedule(self, &participant).required_check_ins() == 0
         {
-            return Err(Error::ParticipantAlreadyJoined { id: self.id });
+            return Err(Error::LateJoinClosed { id: self.id });
         }
 
-        self.participants.push(Participant {
-            id: msg::source(),
-            payment: msg::value(),
-            completed_days: Vec::new(),
-        });
-        self.prize_pool += msg::value();
+        self.participant_index
+            .insert(account, self.participants.len() as u32);
+        self.participants.push(participant);
+        if let Some(team) = team {
+            self.teams[team as usize].members += 1;
+        }
+        self.prize_pool += payment;
 
         Ok(())
     }
 
-    fn sponsor(&mut self) -> Result<(), Error> {
+    fn is_open(&self) -> bool {
+        let joinable = match (self.status, self.late_join) {
+            (ChallengeStatus::Recruiting, _) => true,
+            (ChallengeStatus::Executing, Some(late_join)) => {
+                exec::block_timestamp() / 1000 < self.start_time + late_join.days as u64 * 86400
+            }
+            _ => false,
+        };
+        joinable
+            && (self.teams.is_empty()
+                || self
+                    .teams
+                    .iter()
+                    .any(|team| team.max_members.is_none_or(|max| team.members < max)))
+    }
+
+    fn matches(&self, filter: &ChallengeFilter) -> bool {
+        match filter {
+            ChallengeFilter::All => true,
+            ChallengeFilter::Created(account) => self.creator == *account,
+            ChallengeFilter::Joined(account) => self.participant_index.contains_key(account),
+            ChallengeFilter::Sponsored(account) => self
+                .sponsors
+                .iter()
+                .any(|sponsorship| sponsorship.sponsor == *account),
+            ChallengeFilter::NameContains(text) => {
+                self.name.to_lowercase().contains(&text.to_lowercase())
+            }
+            ChallengeFilter::EntryFee { min, max } => (*min..=*max).contains(&self.entry_fee),
+            ChallengeFilter::StartTime { from, to } => (*from..=*to).contains(&self.start_time),
+            ChallengeFilter::Timezone(timezone) => self.timezone == *timezone,
+            ChallengeFilter::Duration { min_days, max_days } => {
+                let days = (self.end_time - self.start_time) / 86400;
+                (*min_days as u64..=*max_days as u64).contains(&days)
+            }
+            ChallengeFilter::Open => self.is_open(),
+        }
+    }
+
+    fn can_sponsor(&self) -> Result<(), Error> {
         if self.status != ChallengeStatus::Recruiting && self.status != ChallengeStatus::Executing {
             return Err(Error::ChallengeIsNotRecruitingAndNotExecuting {
                 id: self.id,
                 status: self.status,
             });
         }
-        if msg::value() < self.entry_fee {
+
+        Ok(())
+    }
+
+    fn sponsor(&mut self, account: ActorId, payment: u128) -> Result<(), Error> {
+        self.can_sponsor()?;
+        if payment < self.entry_fee {
             return Err(Error::NotEnoughFunds {
                 expected: self.entry_fee,
-                actual: msg::value(),
+                actual: payment,
             });
         }
 
         self.sponsors.push(Sponsorship {
-            sponsor: msg::source(),
-            payment: msg::value(),
+            sponsor: account,
+            payment,
         });
+        self.prize_pool += payment;
 
         Ok(())
     }
@@ -153,16 +430,25 @@ impl ChallengeOps for Challenge {
                 status: self.status,
             });
         }
-        if self.participants.len() == 0 {
+        if self.participants.is_empty() {
             self.status = ChallengeStatus::RecruitFailed;
             return Err(Error::RecruitEndedWithNoParticipants { id: self.id });
         }
 
+        // every participant gets the full number of days from their own local midnight
+        let latest_start = self
+            .participants
+            .iter()
+            .map(|participant| participant.start_time)
+            .max()
+            .unwrap_or(self.start_time);
+        self.execution_end_time = self.end_time + (latest_start - self.start_time);
+
         msg::send_delayed(
             exec::program_id(),
             Command::ChallengeExecutionEnded { id: self.id },
             0,
-            seconds_to_blocks(self.end_time - self.start_time) as u32,
+            seconds_to_blocks(self.execution_end_time - self.start_time) as u32,
         )
         .map_err(|_| Error::FailedToScheduleExecutionEnd { id: self.id })?;
 
@@ -170,10 +456,115 @@ impl ChallengeOps for Challenge {
         Ok(())
     }
 
-    fn execution_ended(&mut self) -> Result<(), Error> {
+    fn execution_ended(&mut self) -> Result<Vec<Settlement>, Error> {
         if msg::source() != exec::program_id() {
             return Err(Error::InternalMethodCalledExternally);
         }
+        match self.status {
+            ChallengeStatus::Executing => {
+                self.status = ChallengeStatus::Finalizing;
+                self.finalization = Some(Finalization {
+                    team_tallies: vec![TeamTally::default(); self.teams.len()],
+                    ..Default::default()
+                });
+            }
+            ChallengeStatus::Finalizing => {}
+            status => {
+                return Err(Error::ChallengeIsNotExecutingAndNotFinalizing {
+                    id: self.id,
+                    status,
+                })
+            }
+        }
+
+        let mut progress = self.finalization.take().unwrap_or_default();
+        let mut budget = FINALIZATION_BATCH_SIZE;
+        let mut payouts = FINALIZATION_PAYOUTS_PER_BATCH;
+        let mut settlements = Vec::new();
+
+        while budget > 0 && payouts > 0 && progress.phase != FinalizationPhase::Done {
+            let cursor = progress.cursor as usize;
+            let mut batch_end = (cursor + budget).min(self.participants.len());
+            let batch = &self.participants[cursor..batch_end];
+
+            match progress.phase {
+                FinalizationPhase::Tallying => {
+                    for participant in batch {
+                        tally(self, &mut progress, participant, outcome(self, participant));
+                    }
+                }
+                FinalizationPhase::Paying => {
+                    for (position, participant) in batch.iter().enumerate() {
+                        let (succeeded, accuracy) = outcome(self, participant);
+                        let (winner, prize) = prize(self, &progress, participant, succeeded);
+                        let refund = refund(self, &progress, participant);
+                        let days_completed = participant.completed_days.len() as u16;
+                        settlements.push(Settlement {
+                            account: participant.id,
+                            payment: participant.payment,
+                            succeeded,
+                            winner,
+                            prize,
+                            refund,
+                            days_completed,
+                            longest_streak: participant.completed_days.longest_streak(),
+                            accuracy,
+                        });
+                        if winner {
+                            progress.paid_count += 1;
+                        }
+                        if winner || refund > 0 {
+                            progress.total_paid += prize + refund;
+                            payouts -= 1;
+                            if payouts == 0 {
+                                batch_end = cursor + position + 1;
+                                break;
+                            }
+                        }
+                    }
+                }
+                FinalizationPhase::Done => unreachable!(),
+            }
+
+            budget -= batch_end - cursor;
+            progress.cursor = batch_end as u64;
+
+            if batch_end == self.participants.len() {
+                progress.cursor = 0;
+                progress.phase = match progress.phase {
+                    FinalizationPhase::Tallying => {
+                        close_tally(self, &mut progress);
+                        FinalizationPhase::Paying
+                    }
+                    _ => {
+                        if is_refunded(self, &progress) {
+                            progress.total_paid += self.sponsored();
+                        }
+                        FinalizationPhase::Done
+                    }
+                };
+            }
+        }
+
+        let done = progress.phase == FinalizationPhase::Done;
+        self.finalization = Some(progress);
+
+        if done {
+            self.status = ChallengeStatus::Completed;
+            return Ok(settlements);
+        }
+
+        msg::send(
+            exec::program_id(),
+            Command::ChallengeExecutionEnded { id: self.id },
+            0,
+        )
+        .map_err(|_| Error::FailedToScheduleFinalizationBatch { id: self.id })?;
+
+        Ok(settlements)
+    }
+
+    fn complete_daily(&mut self) -> Result<(u16, u8, bool), Error> {
         if self.status != ChallengeStatus::Executing {
             return Err(Error::ChallengeIsNotExecuting {
                 id: self.id,
@@ -181,55 +572,465 @@ impl ChallengeOps for Challenge {
             });
         }
 
-        self.status = ChallengeStatus::Completed;
+        let index = *self
+            .participant_index
+            .get(&msg::source())
+            .ok_or(Error::ParticipantNotFound { id: self.id })? as usize;
+        let schedule = participant_schedule(self, &self.participants[index]);
+        let participant = &mut self.participants[index];
+
+        let timestamp = exec::block_timestamp() / 1000;
+        if timestamp < participant.start_time {
+            return Err(Error::CheckInNotOpen {
+                id: self.id,
+                opens_at: participant.start_time,
+            });
+        }
+        let day = (timestamp - participant.start_time) / 86400;
+        if !schedule.accepts_check_in(day) {
+            return Err(Error::NotACheckInDay {
+                id: self.id,
+                day: u16::try_from(day).unwrap_or(u16::MAX),
+            });
+        }
+        let day = day as u16;
+
+        if participant.completed_days.contains(day) {
+            return Ok((day, self.tasks_per_day, false));
+        }
+        let tasks = participant
+            .day_progress
+            .get(&day)
+            .map_or(1, |tasks| tasks + 1);
+        if tasks < self.tasks_per_day {
+            participant.day_progress.insert(day, tasks);
+            return Ok((day, tasks, false));
+        }
+        participant.day_progress.remove(&day);
+        participant.completed_days.insert(day);
+
+        Ok((day, tasks, true))
+    }
+
+    fn analytics(&self, now: u64) -> ChallengeAnalytics {
+        let days = (self.end_time - self.start_time) / 86400;
+        let mut daily_completions = vec![0; days as usize];
+        for participant in &self.participants {
+            for day in participant.completed_days.iter() {
+                if let Some(completions) = daily_completions.get_mut(day as usize) {
+                    *completions += 1;
+                }
+            }
+        }
+
+        let projection = Projection::new(self, now);
+        let participant_count = self.participants.len() as u32;
+        let alive_count = (0..self.participants.len())
+            .filter(|index| projection.alive(*index))
+            .count() as u32;
+        let progress = &projection.finalization;
+        ChallengeAnalytics {
+            daily_completions,
+            participant_count,
+            alive_count,
+            alive_bps: (alive_count as u64 * 10000)
+                .checked_div(participant_count as u64)
+                .unwrap_or(0) as u16,
+            projected_prize_per_stake_bps: if progress.total_succeeded_weight == 0 {
+                0
+            } else {
+                mul_div(
+                    self.prize_pool - progress.rolled_over,
+                    10000,
+                    progress.total_succeeded_weight,
+                )
+                .unwrap_or(u128::MAX)
+            },
+        }
+    }
+
+    fn participants_progress(
+        &self,
+        now: u64,
+        offset: usize,
+        count: usize,
+    ) -> Vec<ParticipantProgress> {
+        let projection = Projection::new(self, now);
+        (offset..self.participants.len())
+            .take(count)
+            .map(|index| projection.progress(self, index, now))
+            .collect()
+    }
+
+    fn participant_progress(&self, now: u64, account: &ActorId) -> Option<ParticipantProgress> {
+        let index = *self.participant_index.get(account)? as usize;
+        Some(Projection::new(self, now).progress(self, index, now))
+    }
+
+    fn simulate_payout(
+        &self,
+        account: ActorId,
+        team: Option<u32>,
+        stake: u128,
+        completion: u16,
+        history: &History,
+        now: u64,
+    ) -> Result<PayoutSimulation, Error> {
+        let mut challenge = self.clone();
+        challenge.join(account, stake, team, None, history)?;
+        let joiner = challenge.participants.len() - 1;
+        let completion = completion.min(10000);
+        let own_outcome = (completion == 10000, completion);
+
+        let scenario = |others: &dyn Fn(&Participant) -> (bool, u16)| {
+            let progress = simulate(&challenge, |index, participant| match index == joiner {
+                true => own_outcome,
+                false => others(participant),
+            });
+            let (_, prize) = prize(
+                &challenge,
+                &progress,
+                &challenge.participants[joiner],
+                own_outcome.0,
+            );
+            prize
+        };
+
+        Ok(PayoutSimulation {
+            everyone_finishes: scenario(&|_| (true, 10000)),
+            survivors_finish: scenario(&|participant| {
+                projected_outcome(&challenge, participant, now)
+            }),
+            only_account_finishes: scenario(&|participant| {
+                (false, outcome(&challenge, participant).1)
+            }),
+        })
+    }
+}
 
-        let execution_days = (self.end_time - self.start_time) / 86400;
+/// Outcome of a challenge if every participant still alive made all of their
+/// remaining check-ins, and everyone else made all they still can.
+struct Projection {
+    /// Outcome of each participant, indexed like `Challenge::participants`.
+    outcomes: Vec<(bool, u16)>,
+    finalization: Finalization,
+}
 
-        let succeeded_participants: Vec<_> = self
+impl Projection {
+    fn new(challenge: &Challenge, now: u64) -> Self {
+        let outcomes: Vec<_> = challenge
             .participants
             .iter()
-            .filter(|participant| participant.completed_days.len() as u64 == execution_days)
+            .map(|participant| projected_outcome(challenge, participant, now))
             .collect();
+        let finalization = simulate(challenge, |index, _| outcomes[index]);
+        Self {
+            outcomes,
+            finalization,
+        }
+    }
 
-        let total_succeeded_payment = succeeded_participants
-            .iter()
-            .map(|participant| participant.payment)
-            .sum::<u128>();
+    fn alive(&self, index: usize) -> bool {
+        self.outcomes[index].0
+    }
 
-        for participant in succeeded_participants.iter() {
-            let value = self.prize_pool * participant.payment / total_succeeded_payment;
-            let payload = Event::ChallengePrize {
-                id: self.id,
-                account: participant.id,
-                prize: value,
-            };
-            msg::send(participant.id, payload, value).map_err(|_| Error::SendError)?;
+    fn progress(&self, challenge: &Challenge, index: usize, now: u64) -> ParticipantProgress {
+        let participant = &challenge.participants[index];
+        let (_, projected_prize) = prize(
+            challenge,
+            &self.finalization,
+            participant,
+            self.alive(index),
+        );
+
+        let days = &participant.completed_days;
+        let today = today(participant, now) as u16;
+        let current_streak = match days.contains(today) {
+            true => days.current_streak(today),
+            false => today
+                .checked_sub(1)
+                .map_or(0, |yesterday| days.current_streak(yesterday)),
+        };
+
+        ParticipantProgress {
+            account: participant.id,
+            days_completed: days.len() as u16,
+            current_streak,
+            longest_streak: days.longest_streak(),
+            alive: self.alive(index),
+            projected_prize,
         }
+    }
+}
 
-        Ok(())
+/// Whether a participant would finish if they made every check-in they still
+/// can, and the share of their check-ins they would make then, in basis points.
+fn projected_outcome(challenge: &Challenge, participant: &Participant, now: u64) -> (bool, u16) {
+    let schedule = participant_schedule(challenge, participant);
+    let missed =
+        schedule.missed_check_ins(participant.completed_days.iter(), today(participant, now));
+    let required = schedule.required_check_ins();
+    let accuracy = ((required - missed) * 10000)
+        .checked_div(required)
+        .unwrap_or(10000);
+    (missed == 0, accuracy as u16)
+}
+
+/// Index of a participant's day at `now`, counted from their start, or the
+/// number of their days once these are over.
+fn today(participant: &Participant, now: u64) -> u64 {
+    let days = (participant.end_time - participant.start_time) / 86400;
+    (now.saturating_sub(participant.start_time) / 86400).min(days)
+}
+
+fn check_team(challenge: &Challenge, account: ActorId, team: Option<u32>) -> Result<(), Error> {
+    let id = challenge.id;
+    let Some(team) = team else {
+        return match challenge.teams.is_empty() {
+            true => Ok(()),
+            false => Err(Error::TeamRequired { id }),
+        };
+    };
+    if challenge.teams.is_empty() {
+        return Err(Error::NotATeamChallenge { id });
     }
 
-    fn complete_daily(&mut self) -> Result<(), Error> {
-        if self.status != ChallengeStatus::Executing {
-            return Err(Error::ChallengeIsNotExecuting {
-                id: self.id,
-                status: self.status,
+    let chosen = challenge
+        .teams
+        .get(team as usize)
+        .ok_or(Error::TeamNotFound { id, team })?;
+    if let Some(captained) = challenge
+        .teams
+        .iter()
+        .position(|team| team.captain == Some(account))
+    {
+        if captained != team as usize {
+            return Err(Error::CaptainMustJoinOwnTeam {
+                id,
+                team: captained as u32,
             });
         }
+    }
+    if chosen.max_members.is_some_and(|max| chosen.members >= max) {
+        return Err(Error::TeamIsFull { id, team });
+    }
 
-        let timestamp = exec::block_timestamp() / 1000;
-        let day = ((timestamp - self.start_time) / 86400) as u16;
+    Ok(())
+}
 
-        let participant = self
-            .participants
-            .iter_mut()
-            .find(|participant| participant.id == msg::source())
-            .ok_or(Error::ParticipantNotFound { id: self.id })?;
+/// Picks the team with the highest share of members who finished, breaking ties
+/// by the average accuracy of the members and then by the lower index. A team
+/// without any finisher never wins.
+fn winning_team(teams: &[Team], tallies: &[TeamTally]) -> Option<u32> {
+    let mut winner: Option<usize> = None;
+    for (index, (team, tally)) in teams.iter().zip(tallies).enumerate() {
+        if tally.finished == 0 {
+            continue;
+        }
+        let better = winner.is_none_or(|best| {
+            let (best_team, best_tally) = (&teams[best], &tallies[best]);
+            // the ratios are compared by cross-multiplying with the other team's size
+            let share = tally.finished as u64 * best_team.members as u64;
+            let best_share = best_tally.finished as u64 * team.members as u64;
+            let accuracy = tally.accuracy_sum as u128 * best_team.members as u128;
+            let best_accuracy = best_tally.accuracy_sum as u128 * team.members as u128;
+            share
+                .cmp(&best_share)
+                .then(accuracy.cmp(&best_accuracy))
+                .is_gt()
+        });
+        if better {
+            winner = Some(index);
+        }
+    }
+    winner.map(|index| index as u32)
+}
+
+fn check_requirements(requirements: &EntryRequirements, history: &History) -> Result<(), Error> {
+    let (won, failed) = history.profile.map_or((0, 0), |profile| {
+        (profile.battles_won, profile.battles_failed)
+    });
 
-        if !participant.completed_days.contains(&day) {
-            participant.completed_days.push(day);
+    if let Some(required) = requirements.min_completed_battles {
+        if won < required {
+            return Err(Error::NotEnoughCompletedBattles {
+                required,
+                actual: won,
+            });
         }
+    }
+    if let Some(required) = requirements.min_completion_rate {
+        let finished = won + failed;
+        let actual = if finished == 0 {
+            0
+        } else {
+            (won as u64 * 10000 / finished as u64) as u16
+        };
+        if actual < required {
+            return Err(Error::CompletionRateTooLow { required, actual });
+        }
+    }
+    if let Some(challenge_id) = requirements.required_badge {
+        if !history
+            .badges
+            .iter()
+            .any(|badge| badge.challenge_id == challenge_id)
+        {
+            return Err(Error::MissingRequiredBadge { challenge_id });
+        }
+    }
 
-        Ok(())
+    Ok(())
+}
+
+/// Check-in schedule of a participant, over their own local days. Each of their
+/// days takes the weekday of the challenge day it starts in, counted in the
+/// challenge timezone, so [`Cadence::Weekdays`] rests on the same days for all.
+fn participant_schedule(challenge: &Challenge, participant: &Participant) -> Schedule {
+    let first_day = participant.start_time.saturating_sub(challenge.start_time) / 86400;
+    Schedule::starting_on(
+        challenge.cadence,
+        (participant.end_time - participant.start_time) / 86400,
+        weekday(challenge.start_time + first_day * 86400, challenge.timezone),
+    )
+}
+
+/// Share of the prize pool a participant's stake stands for, reduced for a late
+/// joiner in proportion to the days they took part in, see [`LateJoin`].
+fn prize_weight(challenge: &Challenge, participant: &Participant) -> u128 {
+    let days = (participant.end_time - participant.start_time) / 86400;
+    let challenge_days = (challenge.end_time - challenge.start_time) / 86400;
+    mul_div(participant.payment, days as u128, challenge_days as u128).unwrap_or(0)
+}
+
+/// Adds the outcome of a participant, whether they finished and their accuracy,
+/// to the tallies of a finalization.
+fn tally(
+    challenge: &Challenge,
+    progress: &mut Finalization,
+    participant: &Participant,
+    (succeeded, accuracy): (bool, u16),
+) {
+    if succeeded {
+        progress.succeeded_count += 1;
+    }
+    let weight = prize_weight(challenge, participant);
+    match participant.team {
+        Some(team) => {
+            let tally = &mut progress.team_tallies[team as usize];
+            tally.finished += succeeded as u32;
+            tally.accuracy_sum += accuracy as u64;
+            tally.total_payment += participant.payment;
+            tally.total_weight += weight;
+        }
+        None if succeeded => {
+            progress.total_succeeded_payment += participant.payment;
+            progress.total_succeeded_weight += weight;
+        }
+        None => {}
+    }
+}
+
+/// Settles who shares the prize pool once every participant is tallied.
+fn close_tally(challenge: &Challenge, progress: &mut Finalization) {
+    if !challenge.teams.is_empty() {
+        progress.winning_team = winning_team(&challenge.teams, &progress.team_tallies);
+        let winner = progress
+            .winning_team
+            .map(|team| &progress.team_tallies[team as usize]);
+        progress.total_succeeded_payment = winner.map_or(0, |tally| tally.total_payment);
+        progress.total_succeeded_weight = winner.map_or(0, |tally| tally.total_weight);
+    }
+    progress.rolled_over = rolled_over(challenge, progress);
+}
+
+/// Whether a participant shares the prize pool of a tallied finalization, and
+/// their prize.
+fn prize(
+    challenge: &Challenge,
+    progress: &Finalization,
+    participant: &Participant,
+    succeeded: bool,
+) -> (bool, u128) {
+    let winner = match participant.team {
+        Some(team) => progress.winning_team == Some(team),
+        None => succeeded,
+    };
+    if !winner {
+        return (false, 0);
+    }
+    let prize = mul_div(
+        challenge.prize_pool - progress.rolled_over,
+        prize_weight(challenge, participant),
+        progress.total_succeeded_weight,
+    )
+    .unwrap_or(0);
+    (true, prize)
+}
+
+/// Whether nobody wins the prize pool of a tallied finalization and, with the
+/// challenge keeping no share back, the stakes and sponsorships in it go back to
+/// those who paid them. The sponsors are refunded by the caller.
+pub fn is_refunded(challenge: &Challenge, progress: &Finalization) -> bool {
+    progress.total_succeeded_weight == 0 && challenge.rollover_bps == 0
+}
+
+/// Stake handed back to a participant when the prize pool is refunded.
+fn refund(challenge: &Challenge, progress: &Finalization, participant: &Participant) -> u128 {
+    if is_refunded(challenge, progress) {
+        participant.payment
+    } else {
+        0
+    }
+}
+
+/// Tallies every participant at once, as finalizing the challenge would with
+/// `outcome` giving the result of each of them, without paying anyone.
+fn simulate(
+    challenge: &Challenge,
+    outcome: impl Fn(usize, &Participant) -> (bool, u16),
+) -> Finalization {
+    let mut progress = Finalization {
+        team_tallies: vec![TeamTally::default(); challenge.teams.len()],
+        ..Default::default()
+    };
+    for (index, participant) in challenge.participants.iter().enumerate() {
+        tally(
+            challenge,
+            &mut progress,
+            participant,
+            outcome(index, participant),
+        );
+    }
+    close_tally(challenge, &mut progress);
+    progress.phase = FinalizationPhase::Paying;
+    progress
+}
+
+/// Whether a participant made every required check-in, and the share of them
+/// made in basis points.
+fn outcome(challenge: &Challenge, participant: &Participant) -> (bool, u16) {
+    let schedule = participant_schedule(challenge, participant);
+    let counted = schedule.counted_check_ins(participant.completed_days.iter());
+    let required = schedule.required_check_ins();
+    (counted == required, (counted * 10000 / required) as u16)
+}
+
+/// Part of the prize pool kept back for the next edition of the series: the
+/// configured share of the forfeited stakes, or the whole pool when nobody wins
+/// it. An edition that keeps nothing back and has no winners only hands back
+/// what was carried over into it, see [`is_refunded`].
+fn rolled_over(challenge: &Challenge, progress: &Finalization) -> u128 {
+    if progress.total_succeeded_weight == 0 {
+        return if challenge.rollover_bps > 0 {
+            challenge.prize_pool
+        } else {
+            challenge.carried_over
+        };
     }
+    let forfeited = challenge.prize_pool
+        - challenge.carried_over
+        - challenge.sponsored()
+        - progress.total_succeeded_payment;
+    forfeited * challenge.rollover_bps as u128 / 10000
 }
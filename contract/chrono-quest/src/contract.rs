@@ -1,14 +1,64 @@
-use crate::challenge_ops::ChallengeOps;
+use crate::{
+    archive::{archive, finished_at, ARCHIVE_RETENTION},
+    badges::BadgeRegistry,
+    challenge_ops::{
+        is_refunded, ChallengeConfig, ChallengeOps, History, FINALIZATION_GAS_RESERVE,
+    },
+    indexes::ChallengeIndex,
+    logs::ChallengeLogs,
+    payments::{Inflow, Payments, PAYMENT_REPLY_DEPOSIT},
+    profiles::{challenge_rating, Profiles},
+    series_ops::{SeriesConfig, SeriesOps},
+    time::is_valid_timezone,
+    token,
+};
 use chrono_quest_io::{
-    Challenge, ChallengeFilter, ChallengeStatus, Command, Error, Event, StateQuery, StateReply,
+    ArchivedChallenge, Challenge, ChallengeFilter, ChallengeSort, ChallengeStatus, Command, Error,
+    Event, GlobalStats, LogAction, PayoutKind, PayoutStatus, Series, SortOrder, StateQuery,
+    StateReply, UtcOffset,
 };
-use gstd::{collections::BTreeMap, msg, prelude::*, ActorId};
+use gstd::{collections::BTreeMap, exec, msg, prelude::*, ActorId, MessageId};
+
+#[derive(PartialEq)]
+enum DepositKind {
+    Join,
+    Sponsor,
+}
+
+/// Tokens pulled from an account for a join or sponsorship, waiting for the
+/// token program to confirm the transfer.
+struct PendingDeposit {
+    id: u64,
+    account: ActorId,
+    amount: u128,
+    kind: DepositKind,
+    team: Option<u32>,
+    timezone: Option<i16>,
+}
 
 struct ChronoQuest {
-    challenges: Vec<Challenge>,
+    challenges: BTreeMap<u64, Challenge>,
+    /// Summaries of the challenges dropped from `challenges`.
+    archived: BTreeMap<u64, ArchivedChallenge>,
+    challenge_count: u64,
+    index: ChallengeIndex,
+    /// Live challenges of each account, which archiving drops ids from. An
+    /// account stays in `joined_map` once it has joined anything, so that it
+    /// keeps counting towards `GlobalStats::unique_participants`.
     created_map: BTreeMap<ActorId, Vec<u64>>,
     joined_map: BTreeMap<ActorId, Vec<u64>>,
     sponsored_map: BTreeMap<ActorId, Vec<u64>>,
+    payments: Payments,
+    pending_deposits: BTreeMap<MessageId, PendingDeposit>,
+    badges: BadgeRegistry,
+    profiles: Profiles,
+    series: Vec<Series>,
+    logs: ChallengeLogs,
+    /// Days on which a participant met the quota, over all challenges.
+    completed_days: u64,
+    /// Value available to the program when it last finished handling a
+    /// message, as state reads are not given the balance.
+    value_available: u128,
 }
 
 static mut CHRONO_QUEST_INSTANCE: Option<ChronoQuest> = None;
@@ -16,10 +66,21 @@ static mut CHRONO_QUEST_INSTANCE: Option<ChronoQuest> = None;
 impl ChronoQuest {
     fn new() -> Self {
         Self {
-            challenges: vec![],
+            challenges: BTreeMap::new(),
+            archived: BTreeMap::new(),
+            challenge_count: 0,
+            index: ChallengeIndex::default(),
             created_map: BTreeMap::new(),
             joined_map: BTreeMap::new(),
             sponsored_map: BTreeMap::new(),
+            payments: Payments::default(),
+            pending_deposits: BTreeMap::new(),
+            badges: BadgeRegistry::default(),
+            profiles: Profiles::default(),
+            series: vec![],
+            logs: ChallengeLogs::default(),
+            completed_days: 0,
+            value_available: 0,
         }
     }
 
@@ -37,129 +98,975 @@ impl ChronoQuest {
         }
     }
 
-    pub fn create_challenge(
-        &mut self,
-        name: String,
-        entry_fee: u128,
-        timezone: i8,
-        start_time: u64,
-        end_time: u64,
-    ) -> Result<Event, Error> {
-        let id = self.challenges.len() as u64;
-        let challenge = Challenge::new(id, name, entry_fee, timezone, start_time, end_time)?;
-        self.challenges.push(challenge);
+    pub fn create_challenge(&mut self, config: ChallengeConfig) -> Result<Event, Error> {
+        let id = self.challenge_count;
+        let challenge = Challenge::new(id, config)?;
+        self.index
+            .insert(id, challenge.status, challenge.start_time);
+        self.challenges.insert(id, challenge);
+        self.challenge_count += 1;
         self.created_map.entry(msg::source()).or_default().push(id);
+        self.logs.record(id, msg::source(), LogAction::Created, 0);
         Ok(Event::ChallengeCreated { id })
     }
 
     pub fn total_challenge_count(&self) -> StateReply {
         StateReply::TotalChallengeCount {
-            count: self.challenges.len() as u64,
+            count: self.challenge_count,
         }
     }
 
-    pub fn query_challenge(&self, id: u64) -> StateReply {
-        StateReply::QueryChallenge {
-            challenge: self.challenges[id as usize].clone(),
-        }
+    pub fn query_challenge(&self, id: u64) -> Result<StateReply, Error> {
+        Ok(StateReply::QueryChallenge {
+            challenge: self.get_challenge(id)?.details(),
+        })
     }
 
+    #[allow(clippy::too_many_arguments)]
     pub fn query_challenges(
         &self,
-        filter: ChallengeFilter,
+        filters: Vec<ChallengeFilter>,
         include_recruiting: bool,
         include_recruit_failed: bool,
         include_executing: bool,
+        include_finalizing: bool,
         include_completed: bool,
+        sort: ChallengeSort,
+        order: SortOrder,
         offset: u64,
         count: u64,
     ) -> StateReply {
-        let challenge_ids: Vec<u64> = match filter {
-            ChallengeFilter::All => (0..self.challenges.len() as u64).collect(),
-            ChallengeFilter::Created(account) => {
-                self.created_map.get(&account).cloned().unwrap_or_default()
-            }
-            ChallengeFilter::Joined(account) => {
-                self.joined_map.get(&account).cloned().unwrap_or_default()
-            }
-            ChallengeFilter::Sponsored(account) => self
-                .sponsored_map
-                .get(&account)
-                .cloned()
-                .unwrap_or_default(),
-        };
+        let statuses: Vec<ChallengeStatus> = [
+            (ChallengeStatus::Recruiting, include_recruiting),
+            (ChallengeStatus::RecruitFailed, include_recruit_failed),
+            (ChallengeStatus::Executing, include_executing),
+            (ChallengeStatus::Finalizing, include_finalizing),
+            (ChallengeStatus::Completed, include_completed),
+        ]
+        .into_iter()
+        .filter_map(|(status, included)| included.then_some(status))
+        .collect();
 
-        let challenges: Vec<Challenge> = challenge_ids
-            .into_iter()
-            .filter_map(|id| {
-                let challenge = &self.challenges[id as usize];
-                match challenge.status {
-                    ChallengeStatus::Recruiting if include_recruiting => Some(challenge),
-                    ChallengeStatus::RecruitFailed if include_recruit_failed => Some(challenge),
-                    ChallengeStatus::Executing if include_executing => Some(challenge),
-                    ChallengeStatus::Completed if include_completed => Some(challenge),
+        // candidates come from the narrowest index a filter allows: the account's
+        // own list, which repeats an id for every sponsorship of the same
+        // challenge in the order they were made, then a range of start times,
+        // then the included statuses
+        let mut challenge_ids: Vec<u64> = filters
+            .iter()
+            .find_map(|filter| {
+                let ids = match filter {
+                    ChallengeFilter::Created(account) => self.created_map.get(account),
+                    ChallengeFilter::Joined(account) => self.joined_map.get(account),
+                    ChallengeFilter::Sponsored(account) => self.sponsored_map.get(account),
+                    _ => return None,
+                };
+                Some(ids.cloned().unwrap_or_default())
+            })
+            .or_else(|| {
+                filters.iter().find_map(|filter| match filter {
+                    ChallengeFilter::StartTime { from, to } => {
+                        Some(self.index.starting_between(*from, *to))
+                    }
                     _ => None,
-                }
+                })
             })
-            .skip(offset as usize)
-            .take(count as usize)
-            .map(|challenge| challenge.clone())
+            .unwrap_or_else(|| self.index.with_status(&statuses));
+        challenge_ids.sort_unstable();
+        challenge_ids.dedup();
+
+        let mut challenges: Vec<&Challenge> = challenge_ids
+            .into_iter()
+            .filter_map(|id| self.challenges.get(&id))
+            .filter(|challenge| statuses.contains(&challenge.status))
+            .filter(|challenge| filters.iter().all(|filter| challenge.matches(filter)))
             .collect();
 
-        StateReply::QueryChallenges { challenges }
+        challenges.sort_by(|a, b| {
+            let ordering = match sort {
+                ChallengeSort::Id => a.id.cmp(&b.id),
+                ChallengeSort::PrizePool => a.prize_pool.cmp(&b.prize_pool),
+                ChallengeSort::StartTime => a.start_time.cmp(&b.start_time),
+                ChallengeSort::ParticipantCount => a.participants.len().cmp(&b.participants.len()),
+            };
+            let ordering = match order {
+                SortOrder::Ascending => ordering,
+                SortOrder::Descending => ordering.reverse(),
+            };
+            ordering.then(a.id.cmp(&b.id))
+        });
+
+        StateReply::QueryChallenges {
+            total: challenges.len() as u64,
+            challenges: challenges
+                .into_iter()
+                .skip(offset as usize)
+                .take(count as usize)
+                .map(Challenge::summary)
+                .collect(),
+        }
     }
 
-    pub fn join_challenge(&mut self, id: u64) -> Result<Event, Error> {
-        self.challenges.get_mut(id as usize).unwrap().join()?;
-        self.joined_map.entry(msg::source()).or_default().push(id);
-        Ok(Event::ChallengeJoined { id })
+    pub fn join_challenge(
+        &mut self,
+        id: u64,
+        team: Option<u32>,
+        timezone: Option<i16>,
+    ) -> Result<Event, Error> {
+        let account = msg::source();
+        let challenge = self
+            .challenges
+            .get_mut(&id)
+            .ok_or_else(|| missing_challenge(&self.archived, id))?;
+        let history = history(&self.profiles, &self.badges, &account);
+
+        let Some(token) = challenge.currency else {
+            challenge.join(account, msg::value(), team, timezone, &history)?;
+            self.joined_map.entry(account).or_default().push(id);
+            self.payments.received(None, Inflow::Stake, msg::value());
+            self.logs
+                .record(id, account, LogAction::Joined, msg::value());
+            return Ok(Event::ChallengeJoined { id });
+        };
+
+        if msg::value() != 0 {
+            return Err(Error::ValueNotAccepted {
+                value: msg::value(),
+            });
+        }
+        challenge.can_join(account, team, &history)?;
+        if let Some(timezone) = timezone.filter(|timezone| !is_valid_timezone(*timezone)) {
+            return Err(Error::InvalidTimezone { timezone });
+        }
+        if self.pending_deposits.values().any(|deposit| {
+            deposit.kind == DepositKind::Join && deposit.id == id && deposit.account == account
+        }) {
+            return Err(Error::ParticipantAlreadyJoined { id });
+        }
+
+        let amount = challenge.join_fee();
+        let message_id = token::transfer_from(token, account, exec::program_id(), amount)?;
+        self.pending_deposits.insert(
+            message_id,
+            PendingDeposit {
+                id,
+                account,
+                amount,
+                kind: DepositKind::Join,
+                team,
+                timezone,
+            },
+        );
+        Ok(Event::ChallengeJoinPending { id })
     }
 
-    pub fn sponsor_challenge(&mut self, id: u64) -> Result<Event, Error> {
-        self.challenges.get_mut(id as usize).unwrap().sponsor()?;
-        self.sponsored_map
-            .entry(msg::source())
-            .or_default()
-            .push(id);
-        Ok(Event::ChallengeSponsored { id })
+    pub fn sponsor_challenge(&mut self, id: u64, amount: u128) -> Result<Event, Error> {
+        let account = msg::source();
+        let challenge = self
+            .challenges
+            .get_mut(&id)
+            .ok_or_else(|| missing_challenge(&self.archived, id))?;
+
+        let Some(token) = challenge.currency else {
+            challenge.sponsor(account, msg::value())?;
+            self.sponsored_map.entry(account).or_default().push(id);
+            self.payments
+                .received(None, Inflow::Sponsorship, msg::value());
+            self.logs
+                .record(id, account, LogAction::Sponsored, msg::value());
+            return Ok(Event::ChallengeSponsored { id });
+        };
+
+        if msg::value() != 0 {
+            return Err(Error::ValueNotAccepted {
+                value: msg::value(),
+            });
+        }
+        challenge.can_sponsor()?;
+        if amount < challenge.entry_fee {
+            return Err(Error::NotEnoughFunds {
+                expected: challenge.entry_fee,
+                actual: amount,
+            });
+        }
+
+        let message_id = token::transfer_from(token, account, exec::program_id(), amount)?;
+        self.pending_deposits.insert(
+            message_id,
+            PendingDeposit {
+                id,
+                account,
+                amount,
+                kind: DepositKind::Sponsor,
+                team: None,
+                timezone: None,
+            },
+        );
+        Ok(Event::ChallengeSponsorshipPending { id })
+    }
+
+    /// Completes a token join or sponsorship once the transfer is answered.
+    ///
+    /// The challenge may have moved on while the transfer was in flight, so the
+    /// join or sponsorship is validated again and the tokens are sent back if it
+    /// is no longer accepted.
+    fn settle_deposit(&mut self, deposit: PendingDeposit, transferred: bool) {
+        let PendingDeposit {
+            id,
+            account,
+            amount,
+            kind,
+            team,
+            timezone,
+        } = deposit;
+        // challenges with deposits in flight are not archived
+        let Some(challenge) = self.challenges.get_mut(&id) else {
+            return;
+        };
+        if transferred {
+            let inflow = match kind {
+                DepositKind::Join => Inflow::Stake,
+                DepositKind::Sponsor => Inflow::Sponsorship,
+            };
+            self.payments.received(challenge.currency, inflow, amount);
+        }
+
+        let result = if !transferred {
+            Err(Error::TokenTransferFailed { id })
+        } else {
+            match kind {
+                DepositKind::Join => challenge
+                    .join(
+                        account,
+                        amount,
+                        team,
+                        timezone,
+                        &history(&self.profiles, &self.badges, &account),
+                    )
+                    .map(|_| {
+                        self.joined_map.entry(account).or_default().push(id);
+                        self.logs.record(id, account, LogAction::Joined, amount);
+                        Event::ChallengeJoined { id }
+                    }),
+                DepositKind::Sponsor => challenge.sponsor(account, amount).map(|_| {
+                    self.sponsored_map.entry(account).or_default().push(id);
+                    self.logs.record(id, account, LogAction::Sponsored, amount);
+                    Event::ChallengeSponsored { id }
+                }),
+            }
+        };
+
+        if transferred && result.is_err() {
+            self.payments.send(
+                Some(id),
+                account,
+                amount,
+                challenge.currency,
+                PayoutKind::Refund,
+                Event::ChallengeRefund {
+                    id,
+                    account,
+                    refund: amount,
+                },
+            );
+            self.logs.record(id, account, LogAction::Refunded, amount);
+        }
+
+        let _ = msg::send(account, result, 0);
+    }
+
+    pub fn handle_reply(&mut self, message_id: MessageId, succeeded: bool) {
+        match self.pending_deposits.remove(&message_id) {
+            Some(deposit) => {
+                let transferred = succeeded && token::transfer_confirmed();
+                self.settle_deposit(deposit, transferred);
+            }
+            None => self.payments.resolve(message_id, succeeded),
+        }
     }
 
     pub fn recruitment_ended(&mut self, id: u64) -> Result<Event, Error> {
-        self.challenges
-            .get_mut(id as usize)
-            .unwrap()
-            .recruitment_ended()?;
+        let challenge = self
+            .challenges
+            .get_mut(&id)
+            .ok_or_else(|| missing_challenge(&self.archived, id))?;
+        let status = challenge.status;
+        let result = challenge.recruitment_ended();
+        self.index.set_status(id, status, challenge.status);
+        if challenge.status != status {
+            self.logs.record(
+                id,
+                exec::program_id(),
+                LogAction::RecruitmentEnded {
+                    status: challenge.status,
+                },
+                0,
+            );
+        }
+
+        if let Err(Error::RecruitEndedWithNoParticipants { .. }) = result {
+            // the jackpot carried into an edition nobody joined waits for the next one
+            if let Some(series) = challenge.series {
+                self.series[series as usize].carry += challenge.carried_over;
+            }
+            for sponsorship in challenge.sponsors.iter() {
+                self.payments.send(
+                    Some(id),
+                    sponsorship.sponsor,
+                    sponsorship.payment,
+                    challenge.currency,
+                    PayoutKind::Refund,
+                    Event::ChallengeRefund {
+                        id,
+                        account: sponsorship.sponsor,
+                        refund: sponsorship.payment,
+                    },
+                );
+                self.logs.record(
+                    id,
+                    sponsorship.sponsor,
+                    LogAction::Refunded,
+                    sponsorship.payment,
+                );
+            }
+        }
+
+        result?;
         Ok(Event::ChallengeRecruitmentEnded {
             id,
-            status: self.challenges[id as usize].status,
+            status: self.challenges[&id].status,
         })
     }
 
     pub fn excution_ended(&mut self, id: u64) -> Result<Event, Error> {
-        self.challenges
-            .get_mut(id as usize)
-            .unwrap()
-            .execution_ended()?;
-        Ok(Event::ChallengeExecutionEnded { id })
+        let challenge = self
+            .challenges
+            .get_mut(&id)
+            .ok_or_else(|| missing_challenge(&self.archived, id))?;
+        let status = challenge.status;
+        let settlements = challenge.execution_ended()?;
+        self.index.set_status(id, status, challenge.status);
+        let rating = challenge_rating(
+            challenge
+                .finalization
+                .as_ref()
+                .map_or(0, |progress| progress.succeeded_count),
+            challenge.participants.len() as u64,
+        );
+
+        for settlement in settlements {
+            self.profiles.record(&settlement, rating);
+            if settlement.winner {
+                pay_out(
+                    &mut self.payments,
+                    id,
+                    settlement.account,
+                    settlement.prize,
+                    challenge.currency,
+                    PayoutKind::Prize,
+                    Event::ChallengePrize {
+                        id,
+                        account: settlement.account,
+                        prize: settlement.prize,
+                    },
+                );
+                self.logs.record(
+                    id,
+                    settlement.account,
+                    LogAction::PrizeSent,
+                    settlement.prize,
+                );
+            }
+            if settlement.refund > 0 {
+                pay_out(
+                    &mut self.payments,
+                    id,
+                    settlement.account,
+                    settlement.refund,
+                    challenge.currency,
+                    PayoutKind::Refund,
+                    Event::ChallengeRefund {
+                        id,
+                        account: settlement.account,
+                        refund: settlement.refund,
+                    },
+                );
+                self.logs.record(
+                    id,
+                    settlement.account,
+                    LogAction::Refunded,
+                    settlement.refund,
+                );
+            }
+            if !settlement.succeeded {
+                continue;
+            }
+
+            self.badges.mint(
+                settlement.account,
+                id,
+                challenge.name.clone(),
+                settlement.days_completed,
+                settlement.accuracy,
+            );
+        }
+
+        let challenge = &self.challenges[&id];
+        if let (ChallengeStatus::Completed, Some(progress)) =
+            (challenge.status, &challenge.finalization)
+        {
+            if let Some(series) = challenge.series {
+                self.series[series as usize].carry += progress.rolled_over;
+            }
+            if is_refunded(challenge, progress) {
+                for sponsorship in challenge.sponsors.iter() {
+                    pay_out(
+                        &mut self.payments,
+                        id,
+                        sponsorship.sponsor,
+                        sponsorship.payment,
+                        challenge.currency,
+                        PayoutKind::Refund,
+                        Event::ChallengeRefund {
+                            id,
+                            account: sponsorship.sponsor,
+                            refund: sponsorship.payment,
+                        },
+                    );
+                    self.logs.record(
+                        id,
+                        sponsorship.sponsor,
+                        LogAction::Refunded,
+                        sponsorship.payment,
+                    );
+                }
+            }
+            self.logs.record(
+                id,
+                exec::program_id(),
+                LogAction::Completed,
+                progress.total_paid,
+            );
+        }
+
+        match &challenge.finalization {
+            Some(progress) if challenge.status == ChallengeStatus::Finalizing => {
+                Ok(Event::ChallengeFinalizationProgressed {
+                    id,
+                    phase: progress.phase,
+                    cursor: progress.cursor,
+                })
+            }
+            _ => Ok(Event::ChallengeExecutionEnded { id }),
+        }
     }
 
     pub fn complete_daily(&mut self, id: u64) -> Result<Event, Error> {
-        self.challenges
-            .get_mut(id as usize)
-            .unwrap()
-            .complete_daily()?;
+        let challenge = self
+            .challenges
+            .get_mut(&id)
+            .ok_or_else(|| missing_challenge(&self.archived, id))?;
+        let (day, tasks, completed) = challenge.complete_daily()?;
+        if tasks < challenge.tasks_per_day {
+            return Ok(Event::ChallengeTaskCompleted { id, day, tasks });
+        }
+        if completed {
+            self.completed_days += 1;
+            self.logs
+                .record(id, msg::source(), LogAction::DayCompleted { day }, 0);
+        }
         Ok(Event::ChallengeDailyCompleted { id })
     }
+
+    pub fn create_series(&mut self, config: SeriesConfig) -> Result<Event, Error> {
+        let id = self.series.len() as u64;
+        self.series.push(Series::new(id, msg::source(), config)?);
+
+        match self.spawn_edition(id) {
+            Ok(challenge_id) => Ok(Event::SeriesCreated { id, challenge_id }),
+            Err(error) => {
+                self.series.pop();
+                Err(error)
+            }
+        }
+    }
+
+    pub fn spawn_series_challenge(&mut self, id: u64) -> Result<Event, Error> {
+        if msg::source() != exec::program_id() {
+            return Err(Error::InternalMethodCalledExternally);
+        }
+
+        let challenge_id = self.spawn_edition(id)?;
+        Ok(Event::SeriesChallengeSpawned { id, challenge_id })
+    }
+
+    /// Creates the next challenge of a series on behalf of its creator and joins
+    /// every enrolled member whose deposit still covers the entry fee.
+    fn spawn_edition(&mut self, id: u64) -> Result<u64, Error> {
+        let series = self
+            .series
+            .get_mut(id as usize)
+            .ok_or(Error::SeriesNotFound { id })?;
+        let challenge_id = self.challenge_count;
+        let mut challenge = Challenge::new(challenge_id, series.next_edition()?)?;
+        // the end of recruitment is already scheduled for the id, so it is used up
+        // even if the series then fails to schedule its next spawn
+        self.challenge_count += 1;
+        challenge.creator = series.creator;
+        series.edition_spawned(&challenge)?;
+        challenge.carried_over = mem::take(&mut series.carry);
+        challenge.prize_pool += challenge.carried_over;
+        self.logs.record(
+            challenge_id,
+            series.creator,
+            LogAction::Created,
+            challenge.carried_over,
+        );
+
+        let entry_fee = challenge.entry_fee;
+        for member in series
+            .members
+            .iter_mut()
+            .filter(|member| member.balance >= entry_fee)
+        {
+            let history = history(&self.profiles, &self.badges, &member.account);
+            if challenge
+                .join(member.account, entry_fee, None, None, &history)
+                .is_ok()
+            {
+                member.balance -= entry_fee;
+                self.payments
+                    .received(challenge.currency, Inflow::SeriesStake, entry_fee);
+                self.logs
+                    .record(challenge_id, member.account, LogAction::Joined, entry_fee);
+                self.joined_map
+                    .entry(member.account)
+                    .or_default()
+                    .push(challenge_id);
+            }
+        }
+
+        self.created_map
+            .entry(series.creator)
+            .or_default()
+            .push(challenge_id);
+        self.index
+            .insert(challenge_id, challenge.status, challenge.start_time);
+        self.challenges.insert(challenge_id, challenge);
+        Ok(challenge_id)
+    }
+
+    pub fn enroll_in_series(&mut self, id: u64) -> Result<Event, Error> {
+        let balance = self
+            .series
+            .get_mut(id as usize)
+            .ok_or(Error::SeriesNotFound { id })?
+            .enroll(msg::source(), msg::value())?;
+        self.payments
+            .received(None, Inflow::SeriesDeposit, msg::value());
+        Ok(Event::SeriesEnrolled { id, balance })
+    }
+
+    pub fn leave_series(&mut self, id: u64) -> Result<Event, Error> {
+        let account = msg::source();
+        let refund = self
+            .series
+            .get_mut(id as usize)
+            .ok_or(Error::SeriesNotFound { id })?
+            .leave(account)?;
+        if refund > 0 {
+            self.payments.send(
+                None,
+                account,
+                refund,
+                None,
+                PayoutKind::Refund,
+                Event::SeriesLeft { id, refund },
+            );
+        }
+        Ok(Event::SeriesLeft { id, refund })
+    }
+
+    /// Replaces up to `count` challenges that finished more than
+    /// `ARCHIVE_RETENTION` ago with their summaries, lowest ids first.
+    pub fn archive_challenges(&mut self, count: u32) -> Result<Event, Error> {
+        let now = exec::block_timestamp() / 1000;
+        let ids: Vec<u64> = self
+            .index
+            .with_status(&[ChallengeStatus::RecruitFailed, ChallengeStatus::Completed])
+            .into_iter()
+            .filter(|id| {
+                finished_at(&self.challenges[id])
+                    .is_some_and(|finished_at| finished_at + ARCHIVE_RETENTION <= now)
+                    && !self
+                        .pending_deposits
+                        .values()
+                        .any(|deposit| deposit.id == *id)
+            })
+            .take(count as usize)
+            .collect();
+
+        for id in &ids {
+            if let Some(challenge) = self.challenges.remove(id) {
+                self.index
+                    .remove(*id, challenge.status, challenge.start_time);
+                forget(&mut self.created_map, [challenge.creator], *id, true);
+                let participants = challenge
+                    .participants
+                    .iter()
+                    .map(|participant| participant.id);
+                forget(&mut self.joined_map, participants, *id, false);
+                let sponsors = challenge
+                    .sponsors
+                    .iter()
+                    .map(|sponsorship| sponsorship.sponsor);
+                forget(&mut self.sponsored_map, sponsors, *id, true);
+                self.logs
+                    .record(*id, exec::program_id(), LogAction::Archived, 0);
+                let log = self.logs.take(*id);
+                self.archived.insert(*id, archive(&challenge, &log, now));
+            }
+        }
+        Ok(Event::ChallengesArchived { ids })
+    }
+
+    pub fn claim_balance(&mut self, currency: Option<ActorId>) -> Result<Event, Error> {
+        let amount = self.payments.claim(msg::source(), currency)?;
+        Ok(Event::BalanceClaimed { amount })
+    }
+
+    pub fn query_payouts(&self, status: PayoutStatus, offset: u64, count: u64) -> StateReply {
+        StateReply::QueryPayouts {
+            payouts: self
+                .payments
+                .payouts(status)
+                .skip(offset as usize)
+                .take(count as usize)
+                .cloned()
+                .collect(),
+        }
+    }
+
+    pub fn query_badge(&self, id: u64) -> Result<StateReply, Error> {
+        let badge = self.badges.get(id).ok_or(Error::BadgeNotFound { id })?;
+        Ok(StateReply::QueryBadge {
+            badge: badge.clone(),
+        })
+    }
+
+    pub fn query_badges(&self, account: ActorId, offset: u64, count: u64) -> StateReply {
+        StateReply::QueryBadges {
+            badges: self
+                .badges
+                .owned_by(&account)
+                .skip(offset as usize)
+                .take(count as usize)
+                .cloned()
+                .collect(),
+        }
+    }
+
+    pub fn query_profile(&self, account: ActorId) -> StateReply {
+        StateReply::QueryProfile {
+            profile: self.profiles.get(&account).cloned(),
+        }
+    }
+
+    pub fn query_top_profiles(&self, offset: u64, count: u64) -> StateReply {
+        StateReply::QueryTopProfiles {
+            profiles: self
+                .profiles
+                .ranked()
+                .into_iter()
+                .skip(offset as usize)
+                .take(count as usize)
+                .cloned()
+                .collect(),
+        }
+    }
+
+    pub fn query_series(&self, id: u64) -> Result<StateReply, Error> {
+        Ok(StateReply::QuerySeries {
+            series: self.get_series(id)?.clone(),
+        })
+    }
+
+    pub fn query_series_challenges(
+        &self,
+        id: u64,
+        offset: u64,
+        count: u64,
+    ) -> Result<StateReply, Error> {
+        Ok(StateReply::QuerySeriesChallenges {
+            challenges: self
+                .get_series(id)?
+                .challenges
+                .iter()
+                .skip(offset as usize)
+                .take(count as usize)
+                .filter_map(|challenge_id| {
+                    self.challenges
+                        .get(challenge_id)
+                        .map(Challenge::summary)
+                        .or_else(|| {
+                            self.archived
+                                .get(challenge_id)
+                                .map(|archive| archive.summary.clone())
+                        })
+                })
+                .collect(),
+        })
+    }
+
+    pub fn query_participants(
+        &self,
+        id: u64,
+        offset: u64,
+        count: u64,
+    ) -> Result<StateReply, Error> {
+        Ok(StateReply::QueryParticipants {
+            participants: self
+                .get_challenge(id)?
+                .participants
+                .iter()
+                .skip(offset as usize)
+                .take(count as usize)
+                .cloned()
+                .collect(),
+        })
+    }
+
+    pub fn query_participant(&self, id: u64, account: ActorId) -> Result<StateReply, Error> {
+        Ok(StateReply::QueryParticipant {
+            participant: self.get_challenge(id)?.participant(&account).cloned(),
+        })
+    }
+
+    pub fn query_sponsors(&self, id: u64, offset: u64, count: u64) -> Result<StateReply, Error> {
+        Ok(StateReply::QuerySponsors {
+            sponsors: self
+                .get_challenge(id)?
+                .sponsors
+                .iter()
+                .skip(offset as usize)
+                .take(count as usize)
+                .cloned()
+                .collect(),
+        })
+    }
+
+    pub fn query_archived_challenge(&self, id: u64) -> Result<StateReply, Error> {
+        let archive = self
+            .archived
+            .get(&id)
+            .ok_or(Error::ChallengeNotFound { id })?;
+        Ok(StateReply::QueryArchivedChallenge {
+            archive: archive.clone(),
+        })
+    }
+
+    pub fn query_archived_challenges(&self, offset: u64, count: u64) -> StateReply {
+        StateReply::QueryArchivedChallenges {
+            archives: self
+                .archived
+                .values()
+                .skip(offset as usize)
+                .take(count as usize)
+                .cloned()
+                .collect(),
+        }
+    }
+
+    pub fn query_challenge_analytics(&self, id: u64) -> Result<StateReply, Error> {
+        Ok(StateReply::QueryChallengeAnalytics {
+            analytics: self
+                .get_challenge(id)?
+                .analytics(exec::block_timestamp() / 1000),
+        })
+    }
+
+    pub fn query_participants_progress(
+        &self,
+        id: u64,
+        offset: u64,
+        count: u64,
+    ) -> Result<StateReply, Error> {
+        Ok(StateReply::QueryParticipantsProgress {
+            progress: self.get_challenge(id)?.participants_progress(
+                exec::block_timestamp() / 1000,
+                offset as usize,
+                count as usize,
+            ),
+        })
+    }
+
+    pub fn query_participant_progress(
+        &self,
+        id: u64,
+        account: ActorId,
+    ) -> Result<StateReply, Error> {
+        Ok(StateReply::QueryParticipantProgress {
+            progress: self
+                .get_challenge(id)?
+                .participant_progress(exec::block_timestamp() / 1000, &account),
+        })
+    }
+
+    pub fn simulate_payout(
+        &self,
+        id: u64,
+        account: ActorId,
+        team: Option<u32>,
+        stake: u128,
+        completion: u16,
+    ) -> Result<StateReply, Error> {
+        let history = history(&self.profiles, &self.badges, &account);
+        Ok(StateReply::SimulatePayout {
+            simulation: self.get_challenge(id)?.simulate_payout(
+                account,
+                team,
+                stake,
+                completion,
+                &history,
+                exec::block_timestamp() / 1000,
+            )?,
+        })
+    }
+
+    pub fn query_challenge_log(
+        &self,
+        id: u64,
+        offset: u64,
+        count: u64,
+    ) -> Result<StateReply, Error> {
+        if id >= self.challenge_count || self.archived.contains_key(&id) {
+            return Err(missing_challenge(&self.archived, id));
+        }
+        let entries = self.logs.get(id);
+        Ok(StateReply::QueryChallengeLog {
+            total: entries.len() as u64,
+            entries: entries
+                .iter()
+                .skip(offset as usize)
+                .take(count as usize)
+                .cloned()
+                .collect(),
+        })
+    }
+
+    pub fn query_global_stats(&self) -> StateReply {
+        let value_available = self.value_available;
+        let native_escrow = self
+            .payments
+            .totals()
+            .find(|totals| totals.currency.is_none())
+            .map_or(0, |totals| totals.escrow);
+        StateReply::QueryGlobalStats {
+            stats: GlobalStats {
+                currencies: self.payments.totals().cloned().collect(),
+                challenge_count: self.challenge_count,
+                recruiting: self.index.count(ChallengeStatus::Recruiting),
+                recruit_failed: self.index.count(ChallengeStatus::RecruitFailed),
+                executing: self.index.count(ChallengeStatus::Executing),
+                finalizing: self.index.count(ChallengeStatus::Finalizing),
+                completed: self.index.count(ChallengeStatus::Completed),
+                archived: self.archived.len() as u64,
+                unique_participants: self.joined_map.len() as u64,
+                completed_days: self.completed_days,
+                value_available,
+                unaccounted_value: value_available as i128 - native_escrow as i128,
+            },
+        }
+    }
+
+    fn get_challenge(&self, id: u64) -> Result<&Challenge, Error> {
+        self.challenges
+            .get(&id)
+            .ok_or_else(|| missing_challenge(&self.archived, id))
+    }
+
+    fn get_series(&self, id: u64) -> Result<&Series, Error> {
+        self.series
+            .get(id as usize)
+            .ok_or(Error::SeriesNotFound { id })
+    }
+
+    pub fn query_claimable_balance(
+        &self,
+        account: ActorId,
+        currency: Option<ActorId>,
+    ) -> StateReply {
+        StateReply::QueryClaimableBalance {
+            amount: self.payments.claimable(account, currency),
+        }
+    }
+}
+
+/// Sends a finalization payout, or credits it to the recipient's claimable
+/// balance once the message has to keep its gas for the batches still to come.
+fn pay_out(
+    payments: &mut Payments,
+    id: u64,
+    recipient: ActorId,
+    amount: u128,
+    currency: Option<ActorId>,
+    kind: PayoutKind,
+    payload: Event,
+) {
+    if exec::gas_available() < FINALIZATION_GAS_RESERVE + PAYMENT_REPLY_DEPOSIT {
+        payments.credit(Some(id), recipient, amount, currency, kind);
+    } else {
+        payments.send(Some(id), recipient, amount, currency, kind, payload);
+    }
+}
+
+/// Drops challenge `id` from the ids listed for each of `accounts`, and the
+/// accounts left without any when `drop_empty` is set.
+fn forget(
+    map: &mut BTreeMap<ActorId, Vec<u64>>,
+    accounts: impl IntoIterator<Item = ActorId>,
+    id: u64,
+    drop_empty: bool,
+) {
+    for account in accounts {
+        if let Some(ids) = map.get_mut(&account) {
+            ids.retain(|listed| *listed != id);
+            if drop_empty && ids.is_empty() {
+                map.remove(&account);
+            }
+        }
+    }
+}
+
+fn missing_challenge(archived: &BTreeMap<u64, ArchivedChallenge>, id: u64) -> Error {
+    if archived.contains_key(&id) {
+        Error::ChallengeArchived { id }
+    } else {
+        Error::ChallengeNotFound { id }
+    }
+}
+
+fn history<'a>(
+    profiles: &'a Profiles,
+    badges: &'a BadgeRegistry,
+    account: &ActorId,
+) -> History<'a> {
+    History {
+        profile: profiles.get(account),
+        badges: badges.owned_by(account).collect(),
+    }
 }
 
 #[no_mangle]
 pub extern "C" fn init() {
-    ChronoQuest::mut_inst();
+    ChronoQuest::mut_inst().value_available = exec::value_available();
 }
 
 #[no_mangle]
 pub extern "C" fn handle() {
-    let command = msg::load::<Command>().unwrap();
+    let Ok(command) = msg::load::<Command>() else {
+        msg::reply(Err::<Event, _>(Error::DecodeFailed), msg::value()).unwrap();
+        return;
+    };
     let inst = ChronoQuest::mut_inst();
 
     let result = match command {
@@ -169,17 +1076,71 @@ pub extern "C" fn handle() {
             timezone,
             start_time,
             end_time,
-        } => inst.create_challenge(name, entry_fee, timezone, start_time, end_time),
+            cadence,
+            tasks_per_day,
+            window,
+            late_join,
+            currency,
+            requirements,
+            teams,
+        } => inst.create_challenge(ChallengeConfig {
+            name,
+            entry_fee,
+            timezone: timezone.minutes(),
+            start_time,
+            end_time,
+            cadence,
+            tasks_per_day,
+            window,
+            late_join,
+            currency,
+            requirements,
+            teams,
+            series: None,
+            rollover_bps: 0,
+        }),
 
-        Command::JoinChallenge { id } => inst.join_challenge(id),
+        Command::JoinChallenge { id, team, timezone } => {
+            inst.join_challenge(id, team, timezone.map(UtcOffset::minutes))
+        }
 
-        Command::SponsorChallenge { id } => inst.sponsor_challenge(id),
+        Command::SponsorChallenge { id, amount } => inst.sponsor_challenge(id, amount),
 
         Command::ChallengeRecruitmentEnded { id } => inst.recruitment_ended(id),
 
         Command::ChallengeExecutionEnded { id } => inst.excution_ended(id),
 
         Command::CompleteDaily { id } => inst.complete_daily(id),
+
+        Command::ClaimBalance { currency } => inst.claim_balance(currency),
+
+        Command::CreateSeries {
+            name_pattern,
+            entry_fee,
+            timezone,
+            first_start_time,
+            cadence_days,
+            duration_days,
+            max_editions,
+            rollover_bps,
+        } => inst.create_series(SeriesConfig {
+            name_pattern,
+            entry_fee,
+            timezone: timezone.minutes(),
+            first_start_time,
+            cadence_days,
+            duration_days,
+            max_editions,
+            rollover_bps,
+        }),
+
+        Command::SpawnSeriesChallenge { id } => inst.spawn_series_challenge(id),
+
+        Command::EnrollInSeries { id } => inst.enroll_in_series(id),
+
+        Command::LeaveSeries { id } => inst.leave_series(id),
+
+        Command::ArchiveChallenges { count } => inst.archive_challenges(count),
     };
 
     if result.is_err() {
@@ -187,39 +1148,127 @@ pub extern "C" fn handle() {
     } else {
         msg::reply(result, 0).unwrap();
     }
+    inst.value_available = exec::value_available();
 }
 
 #[no_mangle]
 pub extern "C" fn state() {
-    let query = msg::load::<StateQuery>().unwrap();
+    let Ok(query) = msg::load::<StateQuery>() else {
+        let error = Error::DecodeFailed;
+        msg::reply(StateReply::Error { error }, msg::value()).unwrap();
+        return;
+    };
     let inst = ChronoQuest::inst();
 
     let result = match query {
-        StateQuery::TotalChallengeCount => inst.total_challenge_count(),
+        StateQuery::TotalChallengeCount => Ok(inst.total_challenge_count()),
 
         StateQuery::QueryChallenge { id } => inst.query_challenge(id),
 
         StateQuery::QueryChallenges {
-            filter,
+            filters,
             include_recruiting,
             include_recruit_failed,
             include_executing,
+            include_finalizing,
             include_completed,
+            sort,
+            order,
             offset,
             count,
-        } => inst.query_challenges(
-            filter,
+        } => Ok(inst.query_challenges(
+            filters,
             include_recruiting,
             include_recruit_failed,
             include_executing,
+            include_finalizing,
             include_completed,
+            sort,
+            order,
             offset,
             count,
-        ),
+        )),
+
+        StateQuery::QueryPayouts {
+            status,
+            offset,
+            count,
+        } => Ok(inst.query_payouts(status, offset, count)),
+
+        StateQuery::QueryClaimableBalance { account, currency } => {
+            Ok(inst.query_claimable_balance(account, currency))
+        }
+
+        StateQuery::QueryBadge { id } => inst.query_badge(id),
+
+        StateQuery::QueryBadges {
+            account,
+            offset,
+            count,
+        } => Ok(inst.query_badges(account, offset, count)),
+
+        StateQuery::QueryProfile { account } => Ok(inst.query_profile(account)),
+
+        StateQuery::QueryTopProfiles { offset, count } => {
+            Ok(inst.query_top_profiles(offset, count))
+        }
+
+        StateQuery::QuerySeries { id } => inst.query_series(id),
+
+        StateQuery::QuerySeriesChallenges { id, offset, count } => {
+            inst.query_series_challenges(id, offset, count)
+        }
+
+        StateQuery::QueryParticipants { id, offset, count } => {
+            inst.query_participants(id, offset, count)
+        }
+
+        StateQuery::QueryParticipant { id, account } => inst.query_participant(id, account),
+
+        StateQuery::QuerySponsors { id, offset, count } => inst.query_sponsors(id, offset, count),
+
+        StateQuery::QueryArchivedChallenge { id } => inst.query_archived_challenge(id),
+
+        StateQuery::QueryArchivedChallenges { offset, count } => {
+            Ok(inst.query_archived_challenges(offset, count))
+        }
+
+        StateQuery::QueryChallengeAnalytics { id } => inst.query_challenge_analytics(id),
+
+        StateQuery::QueryParticipantsProgress { id, offset, count } => {
+            inst.query_participants_progress(id, offset, count)
+        }
+
+        StateQuery::QueryParticipantProgress { id, account } => {
+            inst.query_participant_progress(id, account)
+        }
+
+        StateQuery::SimulatePayout {
+            id,
+            account,
+            team,
+            hypothetical_stake,
+            assumed_completion,
+        } => inst.simulate_payout(id, account, team, hypothetical_stake, assumed_completion),
+
+        StateQuery::QueryChallengeLog { id, offset, count } => {
+            inst.query_challenge_log(id, offset, count)
+        }
+
+        StateQuery::QueryGlobalStats => Ok(inst.query_global_stats()),
     };
 
-    msg::reply(result, msg::value()).unwrap();
+    let reply = result.unwrap_or_else(|error| StateReply::Error { error });
+    msg::reply(reply, msg::value()).unwrap();
 }
 
 #[no_mangle]
-pub extern "C" fn handle_reply() {}
+pub extern "C" fn handle_reply() {
+    let succeeded = msg::reply_code().is_ok_and(|code| code.is_success());
+    let Ok(message_id) = msg::reply_to() else {
+        return;
+    };
+    let inst = ChronoQuest::mut_inst();
+    inst.handle_reply(message_id, succeeded);
+    inst.value_available = exec::value_available();
+}
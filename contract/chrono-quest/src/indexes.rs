@@ -0,0 +1,69 @@
+use chrono_quest_io::ChallengeStatus;
+use gstd::{
+    collections::{BTreeMap, BTreeSet},
+    prelude::*,
+};
+
+/// Challenge ids kept by status and by start time, so listing queries only
+/// visit the challenges they may return.
+#[derive(Default)]
+pub struct ChallengeIndex {
+    by_status: BTreeMap<ChallengeStatus, BTreeSet<u64>>,
+    by_start_time: BTreeSet<(u64, u64)>,
+}
+
+impl ChallengeIndex {
+    pub fn insert(&mut self, id: u64, status: ChallengeStatus, start_time: u64) {
+        self.by_status.entry(status).or_default().insert(id);
+        self.by_start_time.insert((start_time, id));
+    }
+
+    pub fn remove(&mut self, id: u64, status: ChallengeStatus, start_time: u64) {
+        if let Some(ids) = self.by_status.get_mut(&status) {
+            ids.remove(&id);
+        }
+        self.by_start_time.remove(&(start_time, id));
+    }
+
+    pub fn set_status(&mut self, id: u64, from: ChallengeStatus, to: ChallengeStatus) {
+        if from == to {
+            return;
+        }
+        if let Some(ids) = self.by_status.get_mut(&from) {
+            ids.remove(&id);
+        }
+        self.by_status.entry(to).or_default().insert(id);
+    }
+
+    /// Number of challenges in `status`.
+    pub fn count(&self, status: ChallengeStatus) -> u64 {
+        self.by_status
+            .get(&status)
+            .map_or(0, |ids| ids.len() as u64)
+    }
+
+    /// Ids of the challenges in any of the statuses, in ascending order.
+    pub fn with_status(&self, statuses: &[ChallengeStatus]) -> Vec<u64> {
+        let mut ids: Vec<u64> = statuses
+            .iter()
+            .filter_map(|status| self.by_status.get(status))
+            .flatten()
+            .copied()
+            .collect();
+        ids.sort_unstable();
+        ids.dedup();
+        ids
+    }
+
+    /// Ids of the challenges starting within `from..=to`, ordered by start time
+    /// and then by id.
+    pub fn starting_between(&self, from: u64, to: u64) -> Vec<u64> {
+        if from > to {
+            return Vec::new();
+        }
+        self.by_start_time
+            .range((from, 0)..=(to, u64::MAX))
+            .map(|(_, id)| *id)
+            .collect()
+    }
+}
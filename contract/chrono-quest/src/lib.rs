@@ -1,5 +1,14 @@
 #![no_std]
 
+pub mod archive;
+pub mod badges;
 pub mod challenge_ops;
 pub mod contract;
+pub mod indexes;
+pub mod logs;
+pub mod math;
+pub mod payments;
+pub mod profiles;
+pub mod series_ops;
 pub mod time;
+pub mod token;
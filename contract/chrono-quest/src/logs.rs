@@ -0,0 +1,48 @@
+use chrono_quest_io::{Event, LogAction, LogEntry};
+use gstd::{collections::BTreeMap, exec, msg, prelude::*, ActorId};
+
+/// Append-only log of what happened in each challenge, for auditing, until the
+/// challenge is archived.
+///
+/// Every entry is also broadcast as an [`Event::ChallengeLogged`] sent to the
+/// zero address, which off-chain indexers can follow without querying state.
+#[derive(Default)]
+pub struct ChallengeLogs {
+    logs: BTreeMap<u64, Vec<LogEntry>>,
+}
+
+impl ChallengeLogs {
+    /// Appends an entry stamped with the current block to the log of challenge
+    /// `id`, and broadcasts it.
+    pub fn record(&mut self, id: u64, actor: ActorId, action: LogAction, amount: u128) {
+        let entry = LogEntry {
+            block: exec::block_height(),
+            timestamp: exec::block_timestamp() / 1000,
+            actor,
+            action,
+            amount,
+        };
+        // without gas the broadcast skips the mailbox, so it takes nothing from
+        // the gas of the delayed messages sent alongside, and the log is kept
+        // even when it cannot be sent
+        let _ = msg::send_with_gas(
+            ActorId::zero(),
+            Event::ChallengeLogged {
+                id,
+                entry: entry.clone(),
+            },
+            0,
+            0,
+        );
+        self.logs.entry(id).or_default().push(entry);
+    }
+
+    pub fn get(&self, id: u64) -> &[LogEntry] {
+        self.logs.get(&id).map_or(&[], Vec::as_slice)
+    }
+
+    /// Removes the log of challenge `id`, returning its entries.
+    pub fn take(&mut self, id: u64) -> Vec<LogEntry> {
+        self.logs.remove(&id).unwrap_or_default()
+    }
+}
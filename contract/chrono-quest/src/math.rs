@@ -0,0 +1,45 @@
+//! Arithmetic on token amounts, which with 18 decimals leave little headroom
+//! in a `u128` for intermediate products.
+
+/// `a * b / c` rounded down, with the product taken in 256 bits. `None` when
+/// `c` is zero or the result doesn't fit in a `u128`.
+pub fn mul_div(a: u128, b: u128, c: u128) -> Option<u128> {
+    if c == 0 {
+        return None;
+    }
+    let (high, low) = mul_wide(a, b);
+    if high == 0 {
+        return Some(low / c);
+    }
+    if high >= c {
+        return None;
+    }
+
+    // long division of the 256-bit product, one bit of its low half at a time
+    let (mut remainder, mut quotient) = (high, 0u128);
+    for bit in (0..128).rev() {
+        let carry = remainder >> 127;
+        remainder = (remainder << 1) | ((low >> bit) & 1);
+        quotient <<= 1;
+        if carry == 1 || remainder >= c {
+            remainder = remainder.wrapping_sub(c);
+            quotient |= 1;
+        }
+    }
+    Some(quotient)
+}
+
+/// Full 256-bit product of `a` and `b`, as its high and low halves.
+fn mul_wide(a: u128, b: u128) -> (u128, u128) {
+    const MASK: u128 = u64::MAX as u128;
+    let (a_high, a_low) = (a >> 64, a & MASK);
+    let (b_high, b_low) = (b >> 64, b & MASK);
+
+    let low = a_low * b_low;
+    let (cross_a, cross_b) = (a_high * b_low, a_low * b_high);
+    let middle = (low >> 64) + (cross_a & MASK) + (cross_b & MASK);
+    (
+        a_high * b_high + (cross_a >> 64) + (cross_b >> 64) + (middle >> 64),
+        (low & MASK) | (middle << 64),
+    )
+}
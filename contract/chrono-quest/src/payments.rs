@@ -0,0 +1,211 @@
+use crate::token;
+use chrono_quest_io::{CurrencyStats, Error, Event, Payout, PayoutKind, PayoutStatus};
+use gstd::{collections::BTreeMap, exec, msg, prelude::*, ActorId, MessageId};
+
+/// Gas reserved for `handle_reply` when the reply to a payment comes back,
+/// which takes about 11.5 billion.
+pub const PAYMENT_REPLY_DEPOSIT: u64 = 13_000_000_000;
+
+/// Gas taken by sending a message, on top of its reply deposit.
+const SEND_GAS: u64 = 1_000_000_000;
+
+/// Value taken in by the program.
+#[derive(PartialEq, Clone, Copy)]
+pub enum Inflow {
+    Stake,
+    /// A stake paid out of a series deposit the program already holds.
+    SeriesStake,
+    Sponsorship,
+    SeriesDeposit,
+}
+
+/// Outgoing value and token transfers, tracked until their delivery is confirmed,
+/// along with the totals of the value taken in and sent out in each currency.
+///
+/// A payment whose message bounces back is credited to the recipient's
+/// claimable balance, which can be withdrawn later with `ClaimBalance`.
+#[derive(Default)]
+pub struct Payments {
+    pending: BTreeMap<MessageId, Payout>,
+    failed: Vec<Payout>,
+    claimable: BTreeMap<(ActorId, Option<ActorId>), u128>,
+    totals: BTreeMap<Option<ActorId>, CurrencyStats>,
+}
+
+impl Payments {
+    /// Sends `amount` to `recipient`, either as native value carrying `payload` or,
+    /// when `currency` is set, as a transfer of that token.
+    pub fn send(
+        &mut self,
+        challenge_id: Option<u64>,
+        recipient: ActorId,
+        amount: u128,
+        currency: Option<ActorId>,
+        kind: PayoutKind,
+        payload: Event,
+    ) {
+        let payout = Payout {
+            challenge_id,
+            recipient,
+            amount,
+            currency,
+            kind,
+            status: PayoutStatus::Pending,
+        };
+
+        let sent = match currency {
+            Some(token) => token::transfer(token, recipient, amount),
+            None => send_with_reply(recipient, payload.encode(), amount, PAYMENT_REPLY_DEPOSIT),
+        };
+
+        match sent {
+            Ok(message_id) => {
+                self.count(&payout, true);
+                self.pending.insert(message_id, payout);
+            }
+            Err(_) => self.fail(payout),
+        }
+    }
+
+    /// Credits `amount` to the claimable balance of `recipient` without sending
+    /// it, for payouts there is no gas left to send.
+    pub fn credit(
+        &mut self,
+        challenge_id: Option<u64>,
+        recipient: ActorId,
+        amount: u128,
+        currency: Option<ActorId>,
+        kind: PayoutKind,
+    ) {
+        self.fail(Payout {
+            challenge_id,
+            recipient,
+            amount,
+            currency,
+            kind,
+            status: PayoutStatus::Pending,
+        });
+    }
+
+    pub fn resolve(&mut self, message_id: MessageId, succeeded: bool) {
+        let Some(payout) = self.pending.remove(&message_id) else {
+            return;
+        };
+        let delivered = match payout.currency {
+            Some(_) => succeeded && token::transfer_confirmed(),
+            None => succeeded,
+        };
+        if !delivered {
+            self.count(&payout, false);
+            self.fail(payout);
+        }
+    }
+
+    /// Records value taken in for a challenge or a series.
+    pub fn received(&mut self, currency: Option<ActorId>, inflow: Inflow, amount: u128) {
+        let totals = self.totals_mut(currency);
+        match inflow {
+            Inflow::Stake | Inflow::SeriesStake => totals.staked += amount,
+            Inflow::Sponsorship => totals.sponsored += amount,
+            Inflow::SeriesDeposit => totals.deposited += amount,
+        }
+        if inflow != Inflow::SeriesStake {
+            totals.escrow += amount;
+        }
+    }
+
+    pub fn totals(&self) -> impl Iterator<Item = &CurrencyStats> {
+        self.totals.values()
+    }
+
+    pub fn claim(&mut self, account: ActorId, currency: Option<ActorId>) -> Result<u128, Error> {
+        let amount = self
+            .claimable
+            .remove(&(account, currency))
+            .ok_or(Error::NothingToClaim)?;
+        self.send(
+            None,
+            account,
+            amount,
+            currency,
+            PayoutKind::Claim,
+            Event::BalanceClaimed { amount },
+        );
+        Ok(amount)
+    }
+
+    pub fn claimable(&self, account: ActorId, currency: Option<ActorId>) -> u128 {
+        self.claimable
+            .get(&(account, currency))
+            .copied()
+            .unwrap_or_default()
+    }
+
+    pub fn payouts(&self, status: PayoutStatus) -> Box<dyn Iterator<Item = &Payout> + '_> {
+        match status {
+            PayoutStatus::Pending => Box::new(self.pending.values()),
+            PayoutStatus::Failed => Box::new(self.failed.iter()),
+        }
+    }
+
+    /// Adds a payout that was sent to the totals of its currency, or takes it
+    /// back out when it bounced.
+    fn count(&mut self, payout: &Payout, sent: bool) {
+        let totals = self.totals_mut(payout.currency);
+        let total = match payout.kind {
+            PayoutKind::Prize => &mut totals.paid_out,
+            PayoutKind::Refund => &mut totals.refunded,
+            PayoutKind::Claim => &mut totals.claimed,
+        };
+        if sent {
+            *total += payout.amount;
+            totals.escrow = totals.escrow.saturating_sub(payout.amount);
+        } else {
+            *total = total.saturating_sub(payout.amount);
+            totals.escrow += payout.amount;
+        }
+    }
+
+    fn totals_mut(&mut self, currency: Option<ActorId>) -> &mut CurrencyStats {
+        self.totals
+            .entry(currency)
+            .or_insert_with(|| CurrencyStats {
+                currency,
+                ..Default::default()
+            })
+    }
+
+    fn fail(&mut self, mut payout: Payout) {
+        *self
+            .claimable
+            .entry((payout.recipient, payout.currency))
+            .or_default() += payout.amount;
+        payout.status = PayoutStatus::Failed;
+        self.failed.push(payout);
+    }
+}
+
+/// Sends `payload` with `reply_deposit` gas set aside for handling its reply.
+///
+/// Without a deposit the reply is never handled and the transfer stays pending,
+/// so nothing is sent when the message can't afford one. The deposit can only be
+/// made once the message is out, so should it still fail, the message is kept
+/// as sent rather than reported as failed and paid a second time.
+pub fn send_with_reply(
+    destination: ActorId,
+    payload: Vec<u8>,
+    value: u128,
+    reply_deposit: u64,
+) -> Result<MessageId, Error> {
+    let required = reply_deposit + SEND_GAS;
+    let available = exec::gas_available();
+    if available < required {
+        return Err(Error::NotEnoughGasForReply {
+            required,
+            available,
+        });
+    }
+    let message_id = msg::send_bytes(destination, payload, value).map_err(|_| Error::SendError)?;
+    let _ = exec::reply_deposit(message_id, reply_deposit);
+    Ok(message_id)
+}
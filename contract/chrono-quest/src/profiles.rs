@@ -0,0 +1,66 @@
+use crate::challenge_ops::Settlement;
+use chrono_quest_io::Profile;
+use core::cmp::Reverse;
+use gstd::{collections::BTreeMap, prelude::*, ActorId};
+
+pub const INITIAL_REPUTATION: u32 = 1000;
+
+const REPUTATION_K_FACTOR: i64 = 32;
+
+/// Aggregate history of every account that took part in a finished challenge.
+#[derive(Default)]
+pub struct Profiles {
+    profiles: BTreeMap<ActorId, Profile>,
+}
+
+impl Profiles {
+    pub fn get(&self, account: &ActorId) -> Option<&Profile> {
+        self.profiles.get(account)
+    }
+
+    pub fn record(&mut self, settlement: &Settlement, challenge_rating: u32) {
+        let profile = self
+            .profiles
+            .entry(settlement.account)
+            .or_insert_with(|| Profile {
+                account: settlement.account,
+                reputation: INITIAL_REPUTATION,
+                ..Default::default()
+            });
+
+        profile.battles_joined += 1;
+        if settlement.succeeded {
+            profile.battles_won += 1;
+        } else {
+            profile.battles_failed += 1;
+        }
+        profile.total_days_completed += settlement.days_completed as u32;
+        profile.longest_streak = profile.longest_streak.max(settlement.longest_streak);
+        profile.lifetime_staked += settlement.payment;
+        profile.lifetime_won += settlement.prize;
+        profile.reputation =
+            updated_reputation(profile.reputation, challenge_rating, settlement.succeeded);
+    }
+
+    /// Profiles ordered by reputation, highest first.
+    pub fn ranked(&self) -> Vec<&Profile> {
+        let mut ranked: Vec<&Profile> = self.profiles.values().collect();
+        ranked.sort_by_key(|profile| Reverse(profile.reputation));
+        ranked
+    }
+}
+
+/// Rating of a finished challenge seen as an Elo opponent: the fewer
+/// participants finished it, the stronger it is.
+pub fn challenge_rating(succeeded_count: u64, participant_count: u64) -> u32 {
+    (1200 - 400 * succeeded_count / participant_count.max(1)) as u32
+}
+
+/// Elo-style update where the challenge is the opponent, finishing is a win and
+/// failing a loss. The logistic expected score is approximated linearly around
+/// an even match and clamped to 5%..95%.
+fn updated_reputation(reputation: u32, challenge_rating: u32, succeeded: bool) -> u32 {
+    let expected = (5000 + (reputation as i64 - challenge_rating as i64) * 14).clamp(500, 9500);
+    let score = if succeeded { 10000 } else { 0 };
+    (reputation as i64 + REPUTATION_K_FACTOR * (score - expected) / 10000).max(0) as u32
+}
@@ -0,0 +1,180 @@
+use crate::{
+    challenge_ops::{ChallengeConfig, MAX_CHALLENGE_DAYS},
+    time::seconds_to_blocks,
+};
+use chrono_quest_io::{
+    Cadence, Challenge, Command, EntryRequirements, Error, Series, SeriesMember,
+};
+use gstd::{exec, msg, prelude::*, ActorId};
+
+/// Settings a series is created with.
+pub struct SeriesConfig {
+    pub name_pattern: String,
+    pub entry_fee: u128,
+    /// Offset from UTC in minutes.
+    pub timezone: i16,
+    pub first_start_time: u64,
+    pub cadence_days: u32,
+    pub duration_days: u32,
+    pub max_editions: Option<u32>,
+    pub rollover_bps: u16,
+}
+
+pub trait SeriesOps {
+    fn new(id: u64, creator: ActorId, config: SeriesConfig) -> Result<Self, Error>
+    where
+        Self: Sized;
+
+    /// Settings of the next challenge to spawn.
+    fn next_edition(&self) -> Result<ChallengeConfig, Error>;
+
+    /// Records a freshly spawned challenge and schedules the spawn of the next
+    /// one for the moment it starts.
+    fn edition_spawned(&mut self, challenge: &Challenge) -> Result<(), Error>;
+
+    fn enroll(&mut self, account: ActorId, deposit: u128) -> Result<u128, Error>;
+
+    fn leave(&mut self, account: ActorId) -> Result<u128, Error>;
+}
+
+impl SeriesOps for Series {
+    fn new(id: u64, creator: ActorId, config: SeriesConfig) -> Result<Self, Error> {
+        if config.cadence_days == 0 {
+            return Err(Error::InvalidSeriesCadence {
+                days: config.cadence_days,
+            });
+        }
+        if config.rollover_bps > 10000 {
+            return Err(Error::InvalidRolloverRate {
+                bps: config.rollover_bps,
+            });
+        }
+        if config.duration_days == 0 || config.duration_days as u64 > MAX_CHALLENGE_DAYS {
+            return Err(Error::InvalidTimeRange {
+                start: config.first_start_time,
+                end: config.first_start_time + config.duration_days as u64 * 86400,
+            });
+        }
+
+        Ok(Self {
+            id,
+            creator,
+            name_pattern: config.name_pattern,
+            entry_fee: config.entry_fee,
+            timezone: config.timezone,
+            cadence_days: config.cadence_days,
+            duration_days: config.duration_days,
+            max_editions: config.max_editions,
+            rollover_bps: config.rollover_bps,
+            next_start_time: config.first_start_time,
+            carry: 0,
+            challenges: Vec::new(),
+            members: Vec::new(),
+        })
+    }
+
+    fn next_edition(&self) -> Result<ChallengeConfig, Error> {
+        if is_over(self) {
+            return Err(Error::SeriesIsOver { id: self.id });
+        }
+
+        let edition = self.challenges.len() as u32 + 1;
+        let end_time = self.next_start_time + self.duration_days as u64 * 86400;
+        // stakes kept back from an edition that no later spawn follows would be stuck
+        let rollover_bps = if spawns_after(self, edition, end_time) {
+            self.rollover_bps
+        } else {
+            0
+        };
+
+        Ok(ChallengeConfig {
+            name: self.name_pattern.replace("{n}", &edition.to_string()),
+            entry_fee: self.entry_fee,
+            timezone: self.timezone,
+            start_time: self.next_start_time,
+            end_time,
+            cadence: Cadence::Daily,
+            tasks_per_day: 1,
+            window: None,
+            late_join: None,
+            currency: None,
+            requirements: EntryRequirements::default(),
+            teams: Vec::new(),
+            series: Some(self.id),
+            rollover_bps,
+        })
+    }
+
+    fn edition_spawned(&mut self, challenge: &Challenge) -> Result<(), Error> {
+        self.challenges.push(challenge.id);
+        self.next_start_time = challenge.start_time + self.cadence_days as u64 * 86400;
+        if is_over(self) {
+            return Ok(());
+        }
+
+        let now = exec::block_timestamp() / 1000;
+        msg::send_delayed(
+            exec::program_id(),
+            Command::SpawnSeriesChallenge { id: self.id },
+            0,
+            seconds_to_blocks(challenge.start_time - now) as u32,
+        )
+        .map_err(|_| Error::FailedToScheduleSeriesChallenge { id: self.id })?;
+
+        Ok(())
+    }
+
+    fn enroll(&mut self, account: ActorId, deposit: u128) -> Result<u128, Error> {
+        if is_over(self) {
+            return Err(Error::SeriesIsOver { id: self.id });
+        }
+
+        if let Some(member) = self
+            .members
+            .iter_mut()
+            .find(|member| member.account == account)
+        {
+            member.balance += deposit;
+            return Ok(member.balance);
+        }
+
+        if deposit < self.entry_fee {
+            return Err(Error::NotEnoughFunds {
+                expected: self.entry_fee,
+                actual: deposit,
+            });
+        }
+        self.members.push(SeriesMember {
+            account,
+            balance: deposit,
+        });
+
+        Ok(deposit)
+    }
+
+    fn leave(&mut self, account: ActorId) -> Result<u128, Error> {
+        let index = self
+            .members
+            .iter()
+            .position(|member| member.account == account)
+            .ok_or(Error::NotEnrolledInSeries { id: self.id })?;
+
+        Ok(self.members.remove(index).balance)
+    }
+}
+
+/// Whether an edition is still spawned after `time`, given that `edition` is the
+/// one starting at `next_start_time`.
+fn spawns_after(series: &Series, edition: u32, time: u64) -> bool {
+    series.max_editions.is_none_or(|max| {
+        // the last edition is spawned when the one before it starts
+        let cadence = series.cadence_days as u64 * 86400;
+        max > edition && series.next_start_time + (max - edition - 1) as u64 * cadence > time
+    })
+}
+
+fn is_over(series: &Series) -> bool {
+    series
+        .max_editions
+        .is_some_and(|max| series.challenges.len() as u32 >= max)
+}
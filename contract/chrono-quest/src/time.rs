@@ -1,18 +1,161 @@
-fn timestamp_to_local_time(timestamp: u64, timezone: i8) -> i64 {
-    timestamp as i64 + timezone as i64 * 3600
+use chrono_quest_io::{
+    calendar::{self, is_local_midnight, local_day, local_midnight},
+    Cadence,
+};
+use gstd::collections::BTreeMap;
+
+/// Offsets from UTC in use around the world, in minutes.
+pub const TIMEZONE_RANGE: core::ops::RangeInclusive<i16> = -720..=840;
+
+/// Whether `timezone` is a valid offset from UTC in minutes: within
+/// [`TIMEZONE_RANGE`] and a multiple of a quarter hour.
+pub fn is_valid_timezone(timezone: i16) -> bool {
+    TIMEZONE_RANGE.contains(&timezone) && timezone % 15 == 0
 }
 
-pub fn is_start_of_day(timestamp: u64, timezone: i8) -> bool {
-    timestamp_to_local_time(timestamp, timezone) % 86400 == 0
+pub fn is_start_of_day(timestamp: u64, timezone: i16) -> bool {
+    is_local_midnight(timestamp, timezone)
 }
 
 pub fn seconds_to_blocks(seconds: u64) -> u64 {
-    (seconds + 2) / 3
+    seconds.div_ceil(3)
+}
+
+pub fn calculate_future_day_start(start_timestamp: u64, timezone: i16, days: u32) -> u64 {
+    local_midnight(local_day(start_timestamp, timezone) + days as i64, timezone) as u64
+}
+
+/// First local midnight in `timezone` at or after `timestamp`.
+pub fn day_start_at_or_after(timestamp: u64, timezone: i16) -> u64 {
+    if is_start_of_day(timestamp, timezone) {
+        timestamp
+    } else {
+        calculate_future_day_start(timestamp, timezone, 1)
+    }
+}
+
+/// Day of the week of `timestamp` in `timezone`, with Monday as 0.
+pub fn weekday(timestamp: u64, timezone: i16) -> u8 {
+    calendar::weekday(timestamp, timezone).index()
+}
+
+/// Check-in schedule of a challenge, splitting its days into the periods of its
+/// cadence, each with a quota of check-ins.
+pub struct Schedule {
+    cadence: Cadence,
+    execution_days: u64,
+    first_weekday: u8,
+    required: u64,
 }
 
-pub fn calculate_future_day_start(start_timestamp: u64, timezone: i8, days: u32) -> u64 {
-    let local_timestamp = (start_timestamp as i64) + (timezone as i64 * 3600);
-    let current_day_start = local_timestamp - (local_timestamp % 86400);
-    let future_day_start = current_day_start + (days as i64 * 86400);
-    (future_day_start - (timezone as i64 * 3600)) as u64
+impl Schedule {
+    pub fn new(cadence: Cadence, start_time: u64, end_time: u64, timezone: i16) -> Self {
+        Self::starting_on(
+            cadence,
+            (end_time - start_time) / 86400,
+            weekday(start_time, timezone),
+        )
+    }
+
+    /// Schedule of `execution_days` days, the first of them on `first_weekday`
+    /// with Monday as 0.
+    pub fn starting_on(cadence: Cadence, execution_days: u64, first_weekday: u8) -> Self {
+        let mut schedule = Self {
+            cadence,
+            execution_days,
+            first_weekday,
+            required: 0,
+        };
+        schedule.required = (0..schedule.execution_days.div_ceil(schedule.period_length()))
+            .map(|period| schedule.quota(period))
+            .sum();
+        schedule
+    }
+
+    pub fn execution_days(&self) -> u64 {
+        self.execution_days
+    }
+
+    /// Number of check-ins needed to complete the challenge.
+    pub fn required_check_ins(&self) -> u64 {
+        self.required
+    }
+
+    /// Number of check-ins among `completed_days` that count towards completion,
+    /// leaving out those above the quota of their period.
+    pub fn counted_check_ins(&self, completed_days: impl IntoIterator<Item = u16>) -> u64 {
+        if self.period_length() == 1 {
+            return completed_days
+                .into_iter()
+                .filter(|day| self.accepts_check_in(*day as u64))
+                .count() as u64;
+        }
+
+        let mut per_period = BTreeMap::<u64, u64>::new();
+        for day in completed_days
+            .into_iter()
+            .map(|day| day as u64)
+            .filter(|day| *day < self.execution_days)
+        {
+            *per_period.entry(day / self.period_length()).or_default() += 1;
+        }
+        per_period
+            .into_iter()
+            .map(|(period, count)| count.min(self.quota(period)))
+            .sum()
+    }
+
+    /// Number of required check-ins that can no longer be made by `today`, the
+    /// day count since the start, given the check-ins among `completed_days`.
+    pub fn missed_check_ins(
+        &self,
+        completed_days: impl IntoIterator<Item = u16>,
+        today: u64,
+    ) -> u64 {
+        let mut per_period = BTreeMap::<u64, u64>::new();
+        for day in completed_days
+            .into_iter()
+            .map(|day| day as u64)
+            .filter(|day| *day < today.min(self.execution_days))
+        {
+            *per_period.entry(day / self.period_length()).or_default() += 1;
+        }
+
+        let periods = self.execution_days.div_ceil(self.period_length());
+        (0..periods)
+            .take_while(|period| period * self.period_length() < today)
+            .map(|period| {
+                let first_day = period * self.period_length();
+                let end = (first_day + self.period_length()).min(self.execution_days);
+                let days_left = end.saturating_sub(today);
+                let quota = self.quota(period);
+                let made = per_period.get(&period).copied().unwrap_or(0).min(quota);
+                (quota - made).saturating_sub(days_left)
+            })
+            .sum()
+    }
+
+    pub fn accepts_check_in(&self, day: u64) -> bool {
+        day < self.execution_days && self.quota(day / self.period_length()) > 0
+    }
+
+    fn period_length(&self) -> u64 {
+        match self.cadence {
+            Cadence::Daily | Cadence::Weekdays => 1,
+            Cadence::TimesPerWeek(_) => 7,
+            Cadence::EveryKDays(days) => days as u64,
+        }
+    }
+
+    fn quota(&self, period: u64) -> u64 {
+        let first_day = period * self.period_length();
+        match self.cadence {
+            Cadence::Daily | Cadence::EveryKDays(_) => 1,
+            Cadence::TimesPerWeek(times) => {
+                let length = self.period_length().min(self.execution_days - first_day);
+                length.min(times as u64)
+            }
+            Cadence::Weekdays => ((self.first_weekday as u64 + first_day) % 7 < 5) as u64,
+        }
+    }
 }
@@ -0,0 +1,51 @@
+//! Minimal client for fungible token programs exposing the sails `Vft` service.
+
+use crate::payments::{self, PAYMENT_REPLY_DEPOSIT};
+use chrono_quest_io::Error;
+use gstd::{msg, prelude::*, ActorId, MessageId};
+
+const SERVICE: &str = "Vft";
+
+/// Gas reserved for `handle_reply` when a deposit is confirmed, which runs the
+/// whole join or sponsorship.
+pub const DEPOSIT_REPLY_DEPOSIT: u64 = 30_000_000_000;
+
+pub fn transfer(token: ActorId, to: ActorId, value: u128) -> Result<MessageId, Error> {
+    send(token, "Transfer", (to, u256(value)), PAYMENT_REPLY_DEPOSIT)
+}
+
+pub fn transfer_from(
+    token: ActorId,
+    from: ActorId,
+    to: ActorId,
+    value: u128,
+) -> Result<MessageId, Error> {
+    send(
+        token,
+        "TransferFrom",
+        (from, to, u256(value)),
+        DEPOSIT_REPLY_DEPOSIT,
+    )
+}
+
+/// Whether the reply being handled reports a successful transfer.
+pub fn transfer_confirmed() -> bool {
+    msg::load::<(String, String, bool)>().is_ok_and(|(_, _, confirmed)| confirmed)
+}
+
+fn send(
+    token: ActorId,
+    method: &str,
+    args: impl Encode,
+    reply_deposit: u64,
+) -> Result<MessageId, Error> {
+    let payload = (SERVICE, method, args).encode();
+    payments::send_with_reply(token, payload, 0, reply_deposit)
+}
+
+/// SCALE encoding of a `U256`, which is little-endian like `u128`.
+fn u256(value: u128) -> [u8; 32] {
+    let mut bytes = [0; 32];
+    bytes[..16].copy_from_slice(&value.to_le_bytes());
+    bytes
+}
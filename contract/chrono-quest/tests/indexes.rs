@@ -0,0 +1,94 @@
+use chrono_quest::indexes::ChallengeIndex;
+use chrono_quest_io::ChallengeStatus;
+
+const STATUSES: [ChallengeStatus; 5] = [
+    ChallengeStatus::Recruiting,
+    ChallengeStatus::RecruitFailed,
+    ChallengeStatus::Executing,
+    ChallengeStatus::Finalizing,
+    ChallengeStatus::Completed,
+];
+
+/// Small deterministic generator, enough to shuffle the operations around.
+struct Lcg(u64);
+
+impl Lcg {
+    fn next(&mut self, bound: u64) -> u64 {
+        self.0 = self
+            .0
+            .wrapping_mul(6364136223846793005)
+            .wrapping_add(1442695040888963407);
+        (self.0 >> 33) % bound
+    }
+}
+
+fn check(index: &ChallengeIndex, challenges: &[(ChallengeStatus, u64)], rng: &mut Lcg) {
+    for mask in 0..1 << STATUSES.len() {
+        let statuses: Vec<ChallengeStatus> = STATUSES
+            .iter()
+            .enumerate()
+            .filter(|(bit, _)| mask & (1 << bit) != 0)
+            .map(|(_, status)| *status)
+            .collect();
+        let expected: Vec<u64> = (0..challenges.len() as u64)
+            .filter(|id| statuses.contains(&challenges[*id as usize].0))
+            .collect();
+        assert_eq!(index.with_status(&statuses), expected, "{statuses:?}");
+    }
+    for status in STATUSES {
+        let expected = challenges.iter().filter(|(s, _)| *s == status).count();
+        assert_eq!(index.count(status), expected as u64, "{status:?}");
+    }
+
+    for _ in 0..20 {
+        let (from, to) = (rng.next(60) * 3600, rng.next(60) * 3600);
+        let mut expected: Vec<(u64, u64)> = challenges
+            .iter()
+            .enumerate()
+            .map(|(id, (_, start_time))| (*start_time, id as u64))
+            .filter(|(start_time, _)| (from..=to).contains(start_time))
+            .collect();
+        expected.sort();
+        let expected: Vec<u64> = expected.into_iter().map(|(_, id)| id).collect();
+        assert_eq!(index.starting_between(from, to), expected, "{from}..={to}");
+    }
+}
+
+#[test]
+fn test_index_matches_a_full_scan() {
+    let mut rng = Lcg(7);
+    let mut index = ChallengeIndex::default();
+    let mut challenges: Vec<(ChallengeStatus, u64)> = Vec::new();
+
+    for step in 0..3000 {
+        if challenges.is_empty() || rng.next(3) == 0 {
+            let status = STATUSES[rng.next(5) as usize];
+            let start_time = rng.next(50) * 3600;
+            index.insert(challenges.len() as u64, status, start_time);
+            challenges.push((status, start_time));
+        } else {
+            let id = rng.next(challenges.len() as u64);
+            let status = STATUSES[rng.next(5) as usize];
+            index.set_status(id, challenges[id as usize].0, status);
+            challenges[id as usize].0 = status;
+        }
+
+        if step % 100 == 0 {
+            check(&index, &challenges, &mut rng);
+        }
+    }
+    check(&index, &challenges, &mut rng);
+}
+
+#[test]
+fn test_empty_ranges() {
+    let mut index = ChallengeIndex::default();
+    index.insert(0, ChallengeStatus::Recruiting, 100);
+    assert!(index.starting_between(200, 100).is_empty());
+    assert!(index.starting_between(101, 199).is_empty());
+    assert_eq!(index.starting_between(100, 100), vec![0]);
+    assert_eq!(index.starting_between(0, u64::MAX), vec![0]);
+    assert!(index.with_status(&[]).is_empty());
+    assert!(index.with_status(&[ChallengeStatus::Completed]).is_empty());
+    assert_eq!(index.count(ChallengeStatus::Completed), 0);
+}
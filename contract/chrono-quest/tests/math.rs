@@ -0,0 +1,25 @@
+use chrono_quest::math::mul_div;
+
+// 1 token with 18 decimals
+const TOKEN: u128 = 1_000_000_000_000_000_000;
+
+#[test]
+fn test_mul_div_keeps_the_full_product() {
+    assert_eq!(mul_div(7, 3, 2), Some(10));
+    assert_eq!(mul_div(u128::MAX, 1, 1), Some(u128::MAX));
+    assert_eq!(mul_div(u128::MAX, u128::MAX, u128::MAX), Some(u128::MAX));
+    assert_eq!(mul_div(u128::MAX, 3, 4), Some(u128::MAX / 4 * 3 + 2));
+
+    // a prize pool of a billion tokens shared by stakes of a million
+    let pool = 1_000_000_000 * TOKEN;
+    let (stake, total) = (1_000_000 * TOKEN, 3_000_000 * TOKEN);
+    assert!(pool.checked_mul(stake).is_none());
+    assert_eq!(mul_div(pool, stake, total), Some(pool / 3));
+}
+
+#[test]
+fn test_mul_div_rejects_zero_divisor_and_overflow() {
+    assert_eq!(mul_div(1, 1, 0), None);
+    assert_eq!(mul_div(u128::MAX, 2, 1), None);
+    assert_eq!(mul_div(u128::MAX, u128::MAX, u128::MAX - 1), None);
+}
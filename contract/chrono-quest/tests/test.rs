@@ -1,9 +1,75 @@
-use chrono_quest::time::{calculate_future_day_start, seconds_to_blocks};
-use chrono_quest_io::{ChallengeStatus, Command, Error, Event, StateQuery, StateReply};
-use gstd::ActorId;
-use gtest::{constants, Program, System};
+use blake2::{digest::consts::U32, Blake2b, Digest};
+use chrono_quest::{
+    archive::{record_hash, ARCHIVE_RETENTION},
+    challenge_ops::FINALIZATION_PAYOUTS_PER_BATCH,
+    time::{calculate_future_day_start, seconds_to_blocks, weekday},
+};
+use chrono_quest_io::{
+    Badge, Cadence, Challenge, ChallengeAnalytics, ChallengeDetails, ChallengeFilter,
+    ChallengeSort, ChallengeStatus, Command, CurrencyStats, EntryRequirements, Error, Event,
+    FinalizationPhase, GlobalStats, LateJoin, LogAction, LogEntry, Participant,
+    ParticipantProgress, Payout, PayoutKind, PayoutSimulation, PayoutStatus, SortOrder, StateQuery,
+    StateReply, TeamConfig, UtcOffset,
+};
+use gstd::{ActorId, Decode, Encode, MessageId};
+use gtest::{constants, Log, Program, System, WasmProgram};
+use std::{
+    cell::RefCell,
+    collections::{BTreeMap, BTreeSet},
+    rc::Rc,
+};
 
 const USER: u64 = 3;
+const TOKEN: u64 = 50;
+
+/// Fungible token mock answering `Vft/Transfer` and `Vft/TransferFrom` like a sails program.
+#[derive(Debug)]
+struct MockToken {
+    owner: ActorId,
+    balances: Rc<RefCell<BTreeMap<ActorId, u128>>>,
+}
+
+impl WasmProgram for MockToken {
+    fn init(&mut self, _payload: Vec<u8>) -> Result<Option<Vec<u8>>, &'static str> {
+        Ok(None)
+    }
+
+    fn handle(&mut self, payload: Vec<u8>) -> Result<Option<Vec<u8>>, &'static str> {
+        let mut input = payload.as_slice();
+        let (service, method) = <(String, String)>::decode(&mut input).unwrap();
+        let (from, to, value) = match method.as_str() {
+            "Transfer" => {
+                let (to, value) = <(ActorId, [u8; 32])>::decode(&mut input).unwrap();
+                (self.owner, to, value)
+            }
+            "TransferFrom" => <(ActorId, ActorId, [u8; 32])>::decode(&mut input).unwrap(),
+            _ => return Err("unknown method"),
+        };
+        let value = u128::from_le_bytes(value[..16].try_into().unwrap());
+
+        let mut balances = self.balances.borrow_mut();
+        let balance = balances.get(&from).copied().unwrap_or_default();
+        let transferred = balance >= value;
+        if transferred {
+            balances.insert(from, balance - value);
+            *balances.entry(to).or_default() += value;
+        }
+
+        Ok(Some((service, method, transferred).encode()))
+    }
+
+    fn handle_reply(&mut self, _payload: Vec<u8>) -> Result<(), &'static str> {
+        Ok(())
+    }
+
+    fn handle_signal(&mut self, _payload: Vec<u8>) -> Result<(), &'static str> {
+        Ok(())
+    }
+
+    fn state(&mut self) -> Result<Vec<u8>, &'static str> {
+        Ok(Vec::new())
+    }
+}
 
 fn setup_system() -> System {
     let system = System::new();
@@ -12,7 +78,7 @@ fn setup_system() -> System {
     system
 }
 
-fn setup_program(system: &System) -> Program {
+fn setup_program(system: &System) -> Program<'_> {
     let program = Program::current(system);
     let message_id = program.send_bytes(USER, []);
     let block_run_result = system.run_next_block();
@@ -20,29 +86,145 @@ fn setup_program(system: &System) -> Program {
     program
 }
 
+fn challenge_command(system: &System, recruit_duration: u32, execute_duration: u32) -> Command {
+    let current_timestamp = system.block_timestamp() / 1000;
+    let start_time = calculate_future_day_start(current_timestamp, 480, recruit_duration);
+    let end_time =
+        calculate_future_day_start(current_timestamp, 480, recruit_duration + execute_duration);
+
+    Command::CreateChallenge {
+        name: "test".to_string(),
+        entry_fee: 1,
+        timezone: UtcOffset::Hours(8),
+        start_time,
+        end_time,
+        cadence: Cadence::Daily,
+        tasks_per_day: 1,
+        window: None,
+        late_join: None,
+        currency: None,
+        requirements: EntryRequirements::default(),
+        teams: Vec::new(),
+    }
+}
+
+fn submit_challenge(system: &System, program: &Program, command: Command) {
+    let message_id = program.send(USER, command);
+    let block_run_result = system.run_next_block();
+    assert!(block_run_result.succeed.contains(&message_id));
+}
+
 fn setup_challenge(
     system: &System,
     program: &Program,
     recruit_duration: u32,
     execute_duration: u32,
 ) {
-    let current_timestamp = system.block_timestamp() / 1000;
-    let start_time = calculate_future_day_start(current_timestamp, 8, recruit_duration);
-    let end_time =
-        calculate_future_day_start(current_timestamp, 8, recruit_duration + execute_duration);
+    let command = challenge_command(system, recruit_duration, execute_duration);
+    submit_challenge(system, program, command);
+}
 
-    let message_id = program.send(
-        USER,
-        Command::CreateChallenge {
-            name: "test".to_string(),
-            entry_fee: 1,
-            timezone: 8,
-            start_time,
-            end_time,
-        },
-    );
-    let block_run_result = system.run_next_block();
-    assert!(block_run_result.succeed.contains(&message_id));
+fn query_challenge(program: &Program, id: u64) -> ChallengeDetails {
+    let state_reply: StateReply = program
+        .read_state(StateQuery::QueryChallenge { id })
+        .unwrap();
+    match state_reply {
+        StateReply::QueryChallenge { challenge } => challenge,
+        _ => panic!("Expected QueryChallenge variant"),
+    }
+}
+
+fn query_participants(program: &Program, id: u64) -> Vec<Participant> {
+    let state_reply: StateReply = program
+        .read_state(StateQuery::QueryParticipants {
+            id,
+            offset: 0,
+            count: 1000,
+        })
+        .unwrap();
+    match state_reply {
+        StateReply::QueryParticipants { participants } => participants,
+        _ => panic!("Expected QueryParticipants variant"),
+    }
+}
+
+/// Puts a challenge back together from its details and the pages of its
+/// participants and sponsors.
+fn query_full_challenge(program: &Program, id: u64) -> Challenge {
+    let details = query_challenge(program, id);
+    let participants = query_participants(program, id);
+    let state_reply: StateReply = program
+        .read_state(StateQuery::QuerySponsors {
+            id,
+            offset: 0,
+            count: 1000,
+        })
+        .unwrap();
+    let StateReply::QuerySponsors { sponsors } = state_reply else {
+        panic!("Expected QuerySponsors variant");
+    };
+    Challenge {
+        id: details.id,
+        name: details.name,
+        creator: details.creator,
+        entry_fee: details.entry_fee,
+        currency: details.currency,
+        requirements: details.requirements,
+        timezone: details.timezone,
+        creation_time: details.creation_time,
+        start_time: details.start_time,
+        end_time: details.end_time,
+        execution_end_time: details.execution_end_time,
+        cadence: details.cadence,
+        tasks_per_day: details.tasks_per_day,
+        late_join: details.late_join,
+        prize_pool: details.prize_pool,
+        carried_over: details.carried_over,
+        rollover_bps: details.rollover_bps,
+        status: details.status,
+        participant_index: participants
+            .iter()
+            .enumerate()
+            .map(|(index, participant)| (participant.id, index as u32))
+            .collect(),
+        participants,
+        sponsors,
+        teams: details.teams,
+        series: details.series,
+        finalization: details.finalization,
+    }
+}
+
+fn query_payouts(program: &Program, status: PayoutStatus) -> Vec<Payout> {
+    let state_reply: StateReply = program
+        .read_state(StateQuery::QueryPayouts {
+            status,
+            offset: 0,
+            count: 100,
+        })
+        .unwrap();
+    match state_reply {
+        StateReply::QueryPayouts { payouts } => payouts,
+        _ => panic!("Expected QueryPayouts variant"),
+    }
+}
+
+/// Id of the `nonce`th message sent while handling `origin`, derived the way
+/// gear does it, to tell which message sent a logged one.
+fn outgoing_message_id(origin: MessageId, nonce: u32) -> MessageId {
+    let hash: [u8; 32] = Blake2b::<U32>::new()
+        .chain_update(b"outgoing")
+        .chain_update(origin)
+        .chain_update(nonce.to_le_bytes())
+        .finalize()
+        .into();
+    hash.into()
+}
+
+fn run_until(system: &System, timestamp: u64) {
+    while system.block_timestamp() / 1000 < timestamp {
+        system.run_next_block();
+    }
 }
 
 #[test]
@@ -74,9 +256,9 @@ fn test_challenge_recruitment_ended_with_no_participants() {
         let time_before_run = system.block_timestamp() / 1000;
         let block_run_result = system.run_next_block();
         let time_after_run = system.block_timestamp() / 1000;
-        if block_run_result.succeed.len() != 0
-            || block_run_result.failed.len() != 0
-            || block_run_result.log.len() != 0
+        if !block_run_result.succeed.is_empty()
+            || !block_run_result.failed.is_empty()
+            || !block_run_result.log.is_empty()
         {
             assert!(
                 time_before_run < challenge.start_time && challenge.start_time <= time_after_run
@@ -107,7 +289,15 @@ fn test_challenge() {
     setup_challenge(&system, &program, 1, 1);
 
     // join challenge
-    let message_id = program.send_with_value(USER, Command::JoinChallenge { id: 0 }, 1);
+    let message_id = program.send_with_value(
+        USER,
+        Command::JoinChallenge {
+            id: 0,
+            team: None,
+            timezone: None,
+        },
+        1,
+    );
     let block_run_result = system.run_next_block();
     assert!(block_run_result.succeed.contains(&message_id));
 
@@ -121,8 +311,7 @@ fn test_challenge() {
     };
 
     assert_eq!(challenge.status, ChallengeStatus::Recruiting);
-    assert!(challenge
-        .participants
+    assert!(query_participants(&program, 0)
         .iter()
         .any(|p| p.id == ActorId::from(USER)));
 
@@ -134,9 +323,9 @@ fn test_challenge() {
         let time_before_run = system.block_timestamp() / 1000;
         let block_run_result = system.run_next_block();
         let time_after_run = system.block_timestamp() / 1000;
-        if block_run_result.succeed.len() != 0
-            || block_run_result.failed.len() != 0
-            || block_run_result.log.len() != 0
+        if !block_run_result.succeed.is_empty()
+            || !block_run_result.failed.is_empty()
+            || !block_run_result.log.is_empty()
         {
             assert!(
                 time_before_run < challenge.start_time && challenge.start_time <= time_after_run
@@ -171,7 +360,10 @@ fn test_challenge() {
         StateReply::QueryChallenge { challenge } => challenge,
         _ => panic!("Expected QueryChallenge variant"),
     };
-    assert!(challenge.participants[0].completed_days[0] == 0);
+    assert_eq!(challenge.participant_count, 1);
+    assert!(query_participants(&program, 0)[0]
+        .completed_days
+        .contains(0));
 
     let balance_before_complete = system.balance_of(USER);
 
@@ -183,9 +375,9 @@ fn test_challenge() {
         let time_before_run = system.block_timestamp() / 1000;
         let block_run_result = system.run_next_block();
         let time_after_run = system.block_timestamp() / 1000;
-        if block_run_result.succeed.len() != 0
-            || block_run_result.failed.len() != 0
-            || block_run_result.log.len() != 0
+        if !block_run_result.succeed.is_empty()
+            || !block_run_result.failed.is_empty()
+            || !block_run_result.log.is_empty()
         {
             assert!(time_before_run < challenge.end_time && challenge.end_time <= time_after_run);
             break;
@@ -216,7 +408,14 @@ fn test_join_challenge_fails_with_insufficient_fee() {
     let program = setup_program(&system);
     setup_challenge(&system, &program, 1, 1);
 
-    let message_id = program.send(USER, Command::JoinChallenge { id: 0 });
+    let message_id = program.send(
+        USER,
+        Command::JoinChallenge {
+            id: 0,
+            team: None,
+            timezone: None,
+        },
+    );
     let block_run_result = system.run_next_block();
     assert!(block_run_result.succeed.contains(&message_id));
 
@@ -238,9 +437,2632 @@ fn test_join_challenge_fails_with_insufficient_fee() {
         _ => panic!("Expected QueryChallenge variant"),
     };
 
-    assert!(!challenge
-        .participants
+    assert_eq!(challenge.participant_count, 0);
+    assert!(!query_participants(&program, 0)
         .iter()
         .any(|p| p.id == ActorId::from(USER)));
     assert_eq!(challenge.status, ChallengeStatus::Recruiting);
 }
+
+#[test]
+fn test_challenge_finalization_runs_in_batches() {
+    let system = setup_system();
+    let program = setup_program(&system);
+    setup_challenge(&system, &program, 1, 1);
+
+    let users: Vec<u64> = (100..230).collect();
+    for chunk in users.chunks(10) {
+        for &user in chunk {
+            system.mint_to(user, constants::EXISTENTIAL_DEPOSIT * 10);
+            program.send_with_value(
+                user,
+                Command::JoinChallenge {
+                    id: 0,
+                    team: None,
+                    timezone: None,
+                },
+                1,
+            );
+        }
+        system.run_next_block();
+    }
+
+    let challenge = query_challenge(&program, 0);
+    assert_eq!(challenge.participant_count, users.len() as u32);
+
+    run_until(&system, challenge.start_time);
+    assert_eq!(
+        query_challenge(&program, 0).status,
+        ChallengeStatus::Executing
+    );
+
+    // only the even users complete the single execution day
+    for chunk in users.chunks(20) {
+        for &user in chunk.iter().step_by(2) {
+            program.send(user, Command::CompleteDaily { id: 0 });
+        }
+        system.run_next_block();
+    }
+
+    // prizes sent by each finalization message, in the order they ran
+    let mut batches: Vec<(usize, usize)> = Vec::new();
+    run_until(&system, challenge.end_time - 60);
+    while query_challenge(&program, 0).status != ChallengeStatus::Completed {
+        let block_run_result = system.run_next_block();
+        let prizes: Vec<(usize, MessageId)> = block_run_result
+            .log
+            .iter()
+            .enumerate()
+            .filter(|(_, log)| {
+                matches!(
+                    Event::decode(&mut log.payload()),
+                    Ok(Event::ChallengePrize { .. })
+                )
+            })
+            .map(|(position, log)| (position, log.id()))
+            .collect();
+        for origin in &block_run_result.succeed {
+            let sent: BTreeSet<MessageId> = (0..256)
+                .map(|nonce| outgoing_message_id(*origin, nonce))
+                .collect();
+            let positions: Vec<usize> = prizes
+                .iter()
+                .filter(|(_, id)| sent.contains(id))
+                .map(|(position, _)| *position)
+                .collect();
+            if let Some(first) = positions.first() {
+                batches.push((*first, positions.len()));
+            }
+        }
+    }
+    batches.sort_unstable();
+    let batch_sizes: Vec<usize> = batches.iter().map(|(_, size)| *size).collect();
+
+    // the first paying batch shares its message with the last 30 participants
+    // tallied, and the following ones pay up to the limit until gas runs low
+    assert_eq!(batch_sizes[..2], [10, FINALIZATION_PAYOUTS_PER_BATCH]);
+    assert!(batch_sizes
+        .iter()
+        .all(|size| *size <= FINALIZATION_PAYOUTS_PER_BATCH));
+
+    let challenge = query_challenge(&program, 0);
+    let progress = challenge.finalization.unwrap();
+    assert_eq!(progress.phase, FinalizationPhase::Done);
+    assert_eq!(progress.succeeded_count, users.len() as u64 / 2);
+    assert_eq!(progress.paid_count, users.len() as u64 / 2);
+    assert_eq!(progress.total_succeeded_payment, users.len() as u128 / 2);
+    assert_eq!(progress.total_paid, challenge.prize_pool);
+
+    // the prizes the finalization had no gas left to send are claimable instead
+    let (mut total_sent, mut total_credited) = (0, 0);
+    for (i, &user) in users.iter().enumerate() {
+        let prize = Log::builder().payload(Event::ChallengePrize {
+            id: 0,
+            account: ActorId::from(user),
+            prize: 2,
+        });
+        let sent = system.get_mailbox(user).contains(&prize);
+        let state_reply: StateReply = program
+            .read_state(StateQuery::QueryClaimableBalance {
+                account: user.into(),
+                currency: None,
+            })
+            .unwrap();
+        let credited = matches!(state_reply, StateReply::QueryClaimableBalance { amount: 2 });
+        assert!(!(sent && credited));
+        assert_eq!(sent || credited, i % 2 == 0);
+        total_sent += 2 * sent as u128;
+        total_credited += 2 * credited as u128;
+    }
+    assert_eq!(total_sent, 2 * batch_sizes.iter().sum::<usize>() as u128);
+    assert!(total_credited > 0);
+    assert_eq!(total_sent + total_credited, challenge.prize_pool);
+}
+
+#[test]
+fn test_prize_delivery_is_confirmed_by_reply() {
+    let system = setup_system();
+    let program = setup_program(&system);
+    setup_challenge(&system, &program, 1, 1);
+
+    let message_id = program.send_with_value(
+        USER,
+        Command::JoinChallenge {
+            id: 0,
+            team: None,
+            timezone: None,
+        },
+        10,
+    );
+    let block_run_result = system.run_next_block();
+    assert!(block_run_result.succeed.contains(&message_id));
+
+    let challenge = query_challenge(&program, 0);
+    run_until(&system, challenge.start_time);
+    program.send(USER, Command::CompleteDaily { id: 0 });
+    run_until(&system, challenge.end_time);
+    system.run_next_block();
+
+    let pending = query_payouts(&program, PayoutStatus::Pending);
+    assert_eq!(pending.len(), 1);
+    assert_eq!(pending[0].challenge_id, Some(0));
+    assert_eq!(pending[0].recipient, ActorId::from(USER));
+    assert_eq!(pending[0].amount, 10);
+    assert_eq!(pending[0].kind, PayoutKind::Prize);
+
+    let prize = Log::builder().payload(Event::ChallengePrize {
+        id: 0,
+        account: ActorId::from(USER),
+        prize: 10,
+    });
+    system.get_mailbox(USER).claim_value(prize).unwrap();
+    system.run_next_block();
+
+    assert!(query_payouts(&program, PayoutStatus::Pending).is_empty());
+    assert!(query_payouts(&program, PayoutStatus::Failed).is_empty());
+
+    let message_id = program.send(USER, Command::ClaimBalance { currency: None });
+    let block_run_result = system.run_next_block();
+    assert!(block_run_result.succeed.contains(&message_id));
+    let log = block_run_result.decoded_log::<Result<Event, Error>>();
+    assert!(matches!(log[0].payload(), Err(Error::NothingToClaim)));
+}
+
+#[test]
+fn test_unwon_prize_pool_is_refunded() {
+    let system = setup_system();
+    let program = setup_program(&system);
+    let other_user = 4;
+    system.mint_to(other_user, constants::EXISTENTIAL_DEPOSIT * 1000);
+    setup_challenge(&system, &program, 1, 1);
+
+    for (user, stake) in [(USER, 2), (other_user, 4)] {
+        program.send_with_value(
+            user,
+            Command::JoinChallenge {
+                id: 0,
+                team: None,
+                timezone: None,
+            },
+            stake,
+        );
+    }
+    program.send_with_value(USER, Command::SponsorChallenge { id: 0, amount: 0 }, 3);
+    system.run_next_block();
+
+    // nobody completes the single execution day
+    let challenge = query_challenge(&program, 0);
+    run_until(&system, challenge.end_time);
+    system.run_next_block();
+
+    let challenge = query_challenge(&program, 0);
+    assert_eq!(challenge.status, ChallengeStatus::Completed);
+    let finalization = challenge.finalization.unwrap();
+    assert_eq!(finalization.paid_count, 0);
+    assert_eq!(finalization.rolled_over, 0);
+    assert_eq!(finalization.total_paid, 9);
+
+    // each stake and sponsorship goes back to whoever paid it
+    for (user, refund) in [(USER, 2), (USER, 3), (other_user, 4)] {
+        let refund = Log::builder().payload(Event::ChallengeRefund {
+            id: 0,
+            account: ActorId::from(user),
+            refund,
+        });
+        assert!(system.get_mailbox(user).contains(&refund));
+    }
+}
+
+#[test]
+fn test_sponsors_are_refunded_when_recruitment_fails() {
+    let system = setup_system();
+    let program = setup_program(&system);
+    setup_challenge(&system, &program, 1, 1);
+
+    let message_id =
+        program.send_with_value(USER, Command::SponsorChallenge { id: 0, amount: 0 }, 5);
+    let block_run_result = system.run_next_block();
+    assert!(block_run_result.succeed.contains(&message_id));
+
+    let challenge = query_challenge(&program, 0);
+    run_until(&system, challenge.start_time);
+    system.run_next_block();
+    assert_eq!(
+        query_challenge(&program, 0).status,
+        ChallengeStatus::RecruitFailed
+    );
+
+    let pending = query_payouts(&program, PayoutStatus::Pending);
+    assert_eq!(pending.len(), 1);
+    assert_eq!(pending[0].kind, PayoutKind::Refund);
+    assert_eq!(pending[0].amount, 5);
+
+    let refund = Log::builder().payload(Event::ChallengeRefund {
+        id: 0,
+        account: ActorId::from(USER),
+        refund: 5,
+    });
+    assert!(system.get_mailbox(USER).contains(&refund));
+}
+
+#[test]
+fn test_token_challenge() {
+    let system = setup_system();
+    let program = setup_program(&system);
+    let other_user = 4;
+    system.mint_to(other_user, constants::EXISTENTIAL_DEPOSIT * 1000);
+
+    let balances = Rc::new(RefCell::new(BTreeMap::from([(ActorId::from(USER), 100)])));
+    let token = Program::mock_with_id(
+        &system,
+        TOKEN,
+        MockToken {
+            owner: program.id(),
+            balances: balances.clone(),
+        },
+    );
+    token.send_bytes(USER, []);
+    system.run_next_block();
+
+    let mut command = challenge_command(&system, 1, 1);
+    if let Command::CreateChallenge {
+        entry_fee,
+        currency,
+        ..
+    } = &mut command
+    {
+        *entry_fee = 10;
+        *currency = Some(token.id());
+    }
+    submit_challenge(&system, &program, command);
+
+    // native value is refused for token challenges
+    let message_id = program.send_with_value(
+        USER,
+        Command::JoinChallenge {
+            id: 0,
+            team: None,
+            timezone: None,
+        },
+        10,
+    );
+    let block_run_result = system.run_next_block();
+    assert!(block_run_result.succeed.contains(&message_id));
+    let log = block_run_result.decoded_log::<Result<Event, Error>>();
+    assert!(matches!(
+        log[0].payload(),
+        Err(Error::ValueNotAccepted { value: 10 })
+    ));
+
+    // the join only completes once the token transfer is confirmed
+    program.send(
+        USER,
+        Command::JoinChallenge {
+            id: 0,
+            team: None,
+            timezone: None,
+        },
+    );
+    program.send(
+        other_user,
+        Command::JoinChallenge {
+            id: 0,
+            team: None,
+            timezone: None,
+        },
+    );
+    let block_run_result = system.run_next_block();
+    let log = block_run_result.decoded_log::<Result<Event, Error>>();
+    assert!(log
+        .iter()
+        .any(|log| matches!(log.payload(), Ok(Event::ChallengeJoinPending { id: 0 }))));
+    system.run_next_block();
+
+    let challenge = query_challenge(&program, 0);
+    assert_eq!(challenge.participant_count, 1);
+    assert_eq!(query_participants(&program, 0)[0].id, ActorId::from(USER));
+    assert_eq!(challenge.prize_pool, 10);
+    assert_eq!(balances.borrow()[&ActorId::from(USER)], 90);
+    assert_eq!(balances.borrow()[&program.id()], 10);
+
+    let joined: Result<Event, Error> = Ok(Event::ChallengeJoined { id: 0 });
+    assert!(system
+        .get_mailbox(USER)
+        .contains(&Log::builder().payload(joined)));
+    let failed: Result<Event, Error> = Err(Error::TokenTransferFailed { id: 0 });
+    assert!(system
+        .get_mailbox(other_user)
+        .contains(&Log::builder().payload(failed)));
+
+    // the prize is paid back in tokens
+    run_until(&system, challenge.start_time);
+    program.send(USER, Command::CompleteDaily { id: 0 });
+    run_until(&system, challenge.end_time);
+    system.run_next_block();
+    system.run_next_block();
+
+    assert_eq!(
+        query_challenge(&program, 0).status,
+        ChallengeStatus::Completed
+    );
+    assert_eq!(balances.borrow()[&ActorId::from(USER)], 100);
+    assert_eq!(balances.borrow()[&program.id()], 0);
+    assert!(query_payouts(&program, PayoutStatus::Pending).is_empty());
+    assert!(query_payouts(&program, PayoutStatus::Failed).is_empty());
+}
+
+#[test]
+fn test_finishers_receive_badges() {
+    let system = setup_system();
+    let program = setup_program(&system);
+    let other_user = 4;
+    system.mint_to(other_user, constants::EXISTENTIAL_DEPOSIT * 1000);
+    setup_challenge(&system, &program, 1, 1);
+
+    program.send_with_value(
+        USER,
+        Command::JoinChallenge {
+            id: 0,
+            team: None,
+            timezone: None,
+        },
+        1,
+    );
+    program.send_with_value(
+        other_user,
+        Command::JoinChallenge {
+            id: 0,
+            team: None,
+            timezone: None,
+        },
+        1,
+    );
+    system.run_next_block();
+
+    let challenge = query_challenge(&program, 0);
+    run_until(&system, challenge.start_time);
+    program.send(USER, Command::CompleteDaily { id: 0 });
+    run_until(&system, challenge.end_time);
+    system.run_next_block();
+
+    let query_badges = |account: u64| -> Vec<Badge> {
+        let state_reply: StateReply = program
+            .read_state(StateQuery::QueryBadges {
+                account: account.into(),
+                offset: 0,
+                count: 10,
+            })
+            .unwrap();
+        match state_reply {
+            StateReply::QueryBadges { badges } => badges,
+            _ => panic!("Expected QueryBadges variant"),
+        }
+    };
+
+    let badges = query_badges(USER);
+    assert_eq!(badges.len(), 1);
+    assert_eq!(badges[0].owner, ActorId::from(USER));
+    assert_eq!(badges[0].challenge_id, 0);
+    assert_eq!(badges[0].challenge_name, "test");
+    assert_eq!(badges[0].days_completed, 1);
+    assert_eq!(badges[0].accuracy, 10000);
+    assert!(query_badges(other_user).is_empty());
+
+    let state_reply: StateReply = program
+        .read_state(StateQuery::QueryBadge { id: badges[0].id })
+        .unwrap();
+    assert!(matches!(
+        state_reply,
+        StateReply::QueryBadge { badge } if badge.owner == ActorId::from(USER)
+    ));
+}
+
+#[test]
+fn test_profiles_are_updated_at_finalization() {
+    let system = setup_system();
+    let program = setup_program(&system);
+    let other_user = 4;
+    system.mint_to(other_user, constants::EXISTENTIAL_DEPOSIT * 1000);
+    setup_challenge(&system, &program, 1, 1);
+
+    program.send_with_value(
+        USER,
+        Command::JoinChallenge {
+            id: 0,
+            team: None,
+            timezone: None,
+        },
+        3,
+    );
+    program.send_with_value(
+        other_user,
+        Command::JoinChallenge {
+            id: 0,
+            team: None,
+            timezone: None,
+        },
+        1,
+    );
+    system.run_next_block();
+
+    let state_reply: StateReply = program
+        .read_state(StateQuery::QueryProfile {
+            account: USER.into(),
+        })
+        .unwrap();
+    assert!(matches!(
+        state_reply,
+        StateReply::QueryProfile { profile: None }
+    ));
+
+    let challenge = query_challenge(&program, 0);
+    run_until(&system, challenge.start_time);
+    program.send(USER, Command::CompleteDaily { id: 0 });
+    run_until(&system, challenge.end_time);
+    system.run_next_block();
+
+    let state_reply: StateReply = program
+        .read_state(StateQuery::QueryTopProfiles {
+            offset: 0,
+            count: 10,
+        })
+        .unwrap();
+    let profiles = match state_reply {
+        StateReply::QueryTopProfiles { profiles } => profiles,
+        _ => panic!("Expected QueryTopProfiles variant"),
+    };
+    assert_eq!(profiles.len(), 2);
+
+    let winner = &profiles[0];
+    assert_eq!(winner.account, ActorId::from(USER));
+    assert_eq!(winner.battles_joined, 1);
+    assert_eq!(winner.battles_won, 1);
+    assert_eq!(winner.battles_failed, 0);
+    assert_eq!(winner.total_days_completed, 1);
+    assert_eq!(winner.longest_streak, 1);
+    assert_eq!(winner.lifetime_staked, 3);
+    assert_eq!(winner.lifetime_won, 4);
+    assert!(winner.reputation > 1000);
+
+    let loser = &profiles[1];
+    assert_eq!(loser.account, ActorId::from(other_user));
+    assert_eq!(loser.battles_joined, 1);
+    assert_eq!(loser.battles_won, 0);
+    assert_eq!(loser.battles_failed, 1);
+    assert_eq!(loser.total_days_completed, 0);
+    assert_eq!(loser.lifetime_staked, 1);
+    assert_eq!(loser.lifetime_won, 0);
+    assert!(loser.reputation < 1000);
+
+    let state_reply: StateReply = program
+        .read_state(StateQuery::QueryTopProfiles {
+            offset: 1,
+            count: 10,
+        })
+        .unwrap();
+    assert!(matches!(
+        state_reply,
+        StateReply::QueryTopProfiles { profiles } if profiles.len() == 1
+    ));
+}
+
+#[test]
+fn test_entry_requirements_are_checked_on_join() {
+    let system = setup_system();
+    let program = setup_program(&system);
+    let other_user = 4;
+    system.mint_to(other_user, constants::EXISTENTIAL_DEPOSIT * 1000);
+    setup_challenge(&system, &program, 1, 1);
+
+    program.send_with_value(
+        USER,
+        Command::JoinChallenge {
+            id: 0,
+            team: None,
+            timezone: None,
+        },
+        1,
+    );
+    program.send_with_value(
+        other_user,
+        Command::JoinChallenge {
+            id: 0,
+            team: None,
+            timezone: None,
+        },
+        1,
+    );
+    system.run_next_block();
+
+    let challenge = query_challenge(&program, 0);
+    run_until(&system, challenge.start_time);
+    program.send(USER, Command::CompleteDaily { id: 0 });
+    run_until(&system, challenge.end_time);
+    system.run_next_block();
+
+    let mut command = challenge_command(&system, 1, 1);
+    if let Command::CreateChallenge { requirements, .. } = &mut command {
+        requirements.required_badge = Some(0);
+    }
+    submit_challenge(&system, &program, command);
+
+    let mut command = challenge_command(&system, 1, 1);
+    if let Command::CreateChallenge { requirements, .. } = &mut command {
+        requirements.min_completed_battles = Some(1);
+        requirements.min_completion_rate = Some(10000);
+    }
+    submit_challenge(&system, &program, command);
+
+    program.send_with_value(
+        other_user,
+        Command::JoinChallenge {
+            id: 1,
+            team: None,
+            timezone: None,
+        },
+        1,
+    );
+    program.send_with_value(
+        other_user,
+        Command::JoinChallenge {
+            id: 2,
+            team: None,
+            timezone: None,
+        },
+        1,
+    );
+    let block_run_result = system.run_next_block();
+    let log = block_run_result.decoded_log::<Result<Event, Error>>();
+    assert!(log.iter().any(|entry| matches!(
+        entry.payload(),
+        Err(Error::MissingRequiredBadge { challenge_id: 0 })
+    )));
+    assert!(log.iter().any(|entry| matches!(
+        entry.payload(),
+        Err(Error::NotEnoughCompletedBattles {
+            required: 1,
+            actual: 0
+        })
+    )));
+
+    program.send_with_value(
+        USER,
+        Command::JoinChallenge {
+            id: 1,
+            team: None,
+            timezone: None,
+        },
+        1,
+    );
+    program.send_with_value(
+        USER,
+        Command::JoinChallenge {
+            id: 2,
+            team: None,
+            timezone: None,
+        },
+        1,
+    );
+    system.run_next_block();
+
+    for id in [1, 2] {
+        let participants = query_participants(&program, id);
+        assert_eq!(participants.len(), 1);
+        assert_eq!(participants[0].id, ActorId::from(USER));
+    }
+}
+
+#[test]
+fn test_team_challenge() {
+    let system = setup_system();
+    let program = setup_program(&system);
+    let (red_member, blue_member, other_blue_member) = (4, 5, 6);
+    for user in [red_member, blue_member, other_blue_member] {
+        system.mint_to(user, constants::EXISTENTIAL_DEPOSIT * 1000);
+    }
+
+    let mut command = challenge_command(&system, 1, 1);
+    if let Command::CreateChallenge { teams, .. } = &mut command {
+        *teams = vec![
+            TeamConfig {
+                name: "red".to_string(),
+                captain: Some(USER.into()),
+                max_members: Some(2),
+            },
+            TeamConfig {
+                name: "blue".to_string(),
+                captain: None,
+                max_members: None,
+            },
+        ];
+    }
+    submit_challenge(&system, &program, command);
+
+    let join = |user: u64, team: Option<u32>, value: u128| {
+        program.send_with_value(
+            user,
+            Command::JoinChallenge {
+                id: 0,
+                team,
+                timezone: None,
+            },
+            value,
+        );
+        system
+            .run_next_block()
+            .decoded_log::<Result<Event, Error>>()
+    };
+
+    assert!(matches!(
+        join(USER, Some(1), 2)[0].payload(),
+        Err(Error::CaptainMustJoinOwnTeam { id: 0, team: 0 })
+    ));
+    assert!(join(USER, Some(0), 2)[0].payload().is_ok());
+    assert!(join(red_member, Some(0), 1)[0].payload().is_ok());
+    assert!(matches!(
+        join(blue_member, Some(0), 1)[0].payload(),
+        Err(Error::TeamIsFull { id: 0, team: 0 })
+    ));
+    assert!(matches!(
+        join(blue_member, None, 1)[0].payload(),
+        Err(Error::TeamRequired { id: 0 })
+    ));
+    assert!(matches!(
+        join(blue_member, Some(2), 1)[0].payload(),
+        Err(Error::TeamNotFound { id: 0, team: 2 })
+    ));
+    assert!(join(blue_member, Some(1), 1)[0].payload().is_ok());
+    assert!(join(other_blue_member, Some(1), 1)[0].payload().is_ok());
+
+    let challenge = query_challenge(&program, 0);
+    assert_eq!(challenge.teams[0].members, 2);
+    assert_eq!(challenge.teams[1].members, 2);
+
+    run_until(&system, challenge.start_time);
+    for user in [USER, blue_member, other_blue_member] {
+        program.send(user, Command::CompleteDaily { id: 0 });
+    }
+    run_until(&system, challenge.end_time);
+    system.run_next_block();
+
+    let challenge = query_challenge(&program, 0);
+    assert_eq!(challenge.status, ChallengeStatus::Completed);
+    let finalization = challenge.finalization.unwrap();
+    assert_eq!(finalization.winning_team, Some(1));
+    assert_eq!(finalization.team_tallies[0].finished, 1);
+    assert_eq!(finalization.team_tallies[1].finished, 2);
+
+    // the red captain finished too, but only the blue team shares the pool
+    let mut prizes: Vec<(ActorId, u128)> = query_payouts(&program, PayoutStatus::Pending)
+        .into_iter()
+        .map(|payout| (payout.recipient, payout.amount))
+        .collect();
+    prizes.sort();
+    assert_eq!(
+        prizes,
+        vec![(blue_member.into(), 2), (other_blue_member.into(), 2)]
+    );
+}
+
+#[test]
+fn test_series_spawns_challenges_and_auto_enrolls() {
+    let system = setup_system();
+    let program = setup_program(&system);
+    let other_user = 4;
+    system.mint_to(other_user, constants::EXISTENTIAL_DEPOSIT * 1000);
+
+    let first_start_time = calculate_future_day_start(system.block_timestamp() / 1000, 480, 1);
+    submit_challenge(
+        &system,
+        &program,
+        Command::CreateSeries {
+            name_pattern: "Battle #{n}".to_string(),
+            entry_fee: 1,
+            timezone: UtcOffset::Hours(8),
+            first_start_time,
+            cadence_days: 1,
+            duration_days: 1,
+            max_editions: Some(2),
+            rollover_bps: 0,
+        },
+    );
+
+    program.send_with_value(USER, Command::EnrollInSeries { id: 0 }, 2);
+    program.send(other_user, Command::EnrollInSeries { id: 0 });
+    let block_run_result = system.run_next_block();
+    let log = block_run_result.decoded_log::<Result<Event, Error>>();
+    assert!(matches!(
+        log[0].payload(),
+        Ok(Event::SeriesEnrolled { id: 0, balance: 2 })
+    ));
+    assert!(matches!(
+        log[1].payload(),
+        Err(Error::NotEnoughFunds {
+            expected: 1,
+            actual: 0
+        })
+    ));
+
+    run_until(&system, first_start_time);
+    system.run_next_block();
+
+    let state_reply: StateReply = program
+        .read_state(StateQuery::QuerySeriesChallenges {
+            id: 0,
+            offset: 0,
+            count: 10,
+        })
+        .unwrap();
+    let challenges = match state_reply {
+        StateReply::QuerySeriesChallenges { challenges } => challenges,
+        _ => panic!("Expected QuerySeriesChallenges variant"),
+    };
+    assert_eq!(challenges.len(), 2);
+    assert_eq!(challenges[0].name, "Battle #1");
+    assert_eq!(challenges[0].status, ChallengeStatus::RecruitFailed);
+    assert_eq!(challenges[1].name, "Battle #2");
+    assert_eq!(challenges[1].series, Some(0));
+    assert_eq!(challenges[1].creator, ActorId::from(USER));
+    assert_eq!(challenges[1].start_time, first_start_time + 86400);
+    assert_eq!(challenges[1].participant_count, 1);
+    assert_eq!(query_participants(&program, 1)[0].id, ActorId::from(USER));
+
+    let message_id = program.send(USER, Command::LeaveSeries { id: 0 });
+    let block_run_result = system.run_next_block();
+    assert!(block_run_result.succeed.contains(&message_id));
+    let refund = Log::builder().payload(Event::SeriesLeft { id: 0, refund: 1 });
+    assert!(system.get_mailbox(USER).contains(&refund));
+
+    run_until(&system, first_start_time + 86400);
+    system.run_next_block();
+
+    let state_reply: StateReply = program
+        .read_state(StateQuery::QuerySeries { id: 0 })
+        .unwrap();
+    let series = match state_reply {
+        StateReply::QuerySeries { series } => series,
+        _ => panic!("Expected QuerySeries variant"),
+    };
+    assert_eq!(series.challenges, vec![0, 1]);
+    assert!(series.members.is_empty());
+}
+
+#[test]
+fn test_series_rolls_forfeited_stakes_over() {
+    let system = setup_system();
+    let program = setup_program(&system);
+    let other_user = 4;
+    system.mint_to(other_user, constants::EXISTENTIAL_DEPOSIT * 1000);
+
+    let first_start_time = calculate_future_day_start(system.block_timestamp() / 1000, 480, 1);
+    submit_challenge(
+        &system,
+        &program,
+        Command::CreateSeries {
+            name_pattern: "Season {n}".to_string(),
+            entry_fee: 1,
+            timezone: UtcOffset::Hours(8),
+            first_start_time,
+            cadence_days: 1,
+            duration_days: 1,
+            max_editions: None,
+            rollover_bps: 5000,
+        },
+    );
+
+    program.send_with_value(
+        USER,
+        Command::JoinChallenge {
+            id: 0,
+            team: None,
+            timezone: None,
+        },
+        2,
+    );
+    program.send_with_value(
+        other_user,
+        Command::JoinChallenge {
+            id: 0,
+            team: None,
+            timezone: None,
+        },
+        2,
+    );
+    system.run_next_block();
+
+    run_until(&system, first_start_time);
+    program.send(USER, Command::CompleteDaily { id: 0 });
+    run_until(&system, first_start_time + 2 * 86400);
+    system.run_next_block();
+
+    let challenge = query_challenge(&program, 0);
+    assert_eq!(challenge.status, ChallengeStatus::Completed);
+    let finalization = challenge.finalization.unwrap();
+    assert_eq!(finalization.rolled_over, 1);
+    assert_eq!(finalization.paid_count, 1);
+    assert_eq!(finalization.total_paid, 3);
+
+    // the kept back stake seeds whichever edition is spawned next, and as nobody
+    // joins that one, it moves on to the next edition spawned after it fails
+    run_until(&system, first_start_time + 3 * 86400);
+    system.run_next_block();
+
+    let state_reply: StateReply = program
+        .read_state(StateQuery::QuerySeriesChallenges {
+            id: 0,
+            offset: 1,
+            count: 10,
+        })
+        .unwrap();
+    let challenges = match state_reply {
+        StateReply::QuerySeriesChallenges { challenges } => challenges,
+        _ => panic!("Expected QuerySeriesChallenges variant"),
+    };
+    let seeded: Vec<(u64, ChallengeStatus, u128, u128)> = challenges
+        .iter()
+        .filter(|challenge| challenge.carried_over > 0)
+        .map(|challenge| {
+            (
+                challenge.id,
+                challenge.status,
+                challenge.carried_over,
+                challenge.prize_pool,
+            )
+        })
+        .collect();
+    assert_eq!(
+        seeded,
+        vec![
+            (2, ChallengeStatus::RecruitFailed, 1, 1),
+            (4, ChallengeStatus::Recruiting, 1, 1)
+        ]
+    );
+}
+
+#[test]
+fn test_challenge_length_is_limited() {
+    let system = setup_system();
+    let program = setup_program(&system);
+
+    // days are counted in a u16
+    let mut command = challenge_command(&system, 1, 1);
+    let mut range = (0, 0);
+    if let Command::CreateChallenge {
+        start_time,
+        end_time,
+        ..
+    } = &mut command
+    {
+        *end_time = *start_time + (u16::MAX as u64 + 1) * 86400;
+        range = (*start_time, *end_time);
+    }
+    program.send(USER, command);
+    program.send(
+        USER,
+        Command::CreateSeries {
+            name_pattern: "Season {n}".to_string(),
+            entry_fee: 1,
+            timezone: UtcOffset::Hours(8),
+            first_start_time: range.0,
+            cadence_days: 1,
+            duration_days: u16::MAX as u32 + 1,
+            max_editions: None,
+            rollover_bps: 0,
+        },
+    );
+    let block_run_result = system.run_next_block();
+    let log = block_run_result.decoded_log::<Result<Event, Error>>();
+    assert_eq!(log.len(), 2);
+    assert!(log.iter().all(|entry| matches!(
+        entry.payload(),
+        Err(Error::InvalidTimeRange { start, end }) if (*start, *end) == range
+    )));
+}
+
+#[test]
+fn test_every_k_days_challenge() {
+    let system = setup_system();
+    let program = setup_program(&system);
+
+    let mut command = challenge_command(&system, 1, 2);
+    if let Command::CreateChallenge { cadence, .. } = &mut command {
+        *cadence = Cadence::TimesPerWeek(8);
+    }
+    program.send(USER, command.clone());
+    let block_run_result = system.run_next_block();
+    let log = block_run_result.decoded_log::<Result<Event, Error>>();
+    assert!(matches!(
+        log[0].payload(),
+        Err(Error::InvalidCadence {
+            cadence: Cadence::TimesPerWeek(8)
+        })
+    ));
+
+    if let Command::CreateChallenge { cadence, .. } = &mut command {
+        *cadence = Cadence::EveryKDays(2);
+    }
+    submit_challenge(&system, &program, command);
+    program.send_with_value(
+        USER,
+        Command::JoinChallenge {
+            id: 0,
+            team: None,
+            timezone: None,
+        },
+        1,
+    );
+    system.run_next_block();
+
+    // a single check-in on the second day covers the whole two-day period
+    let challenge = query_challenge(&program, 0);
+    run_until(&system, challenge.start_time + 86400);
+    program.send(USER, Command::CompleteDaily { id: 0 });
+    run_until(&system, challenge.end_time);
+    system.run_next_block();
+
+    let challenge = query_challenge(&program, 0);
+    assert_eq!(challenge.status, ChallengeStatus::Completed);
+    assert_eq!(challenge.finalization.unwrap().succeeded_count, 1);
+}
+
+#[test]
+fn test_daily_task_quota() {
+    let system = setup_system();
+    let program = setup_program(&system);
+    let other_user = 4;
+    system.mint_to(other_user, constants::EXISTENTIAL_DEPOSIT * 1000);
+
+    let mut command = challenge_command(&system, 1, 2);
+    if let Command::CreateChallenge { tasks_per_day, .. } = &mut command {
+        *tasks_per_day = 3;
+    }
+    submit_challenge(&system, &program, command);
+    program.send_with_value(
+        USER,
+        Command::JoinChallenge {
+            id: 0,
+            team: None,
+            timezone: None,
+        },
+        1,
+    );
+    program.send_with_value(
+        other_user,
+        Command::JoinChallenge {
+            id: 0,
+            team: None,
+            timezone: None,
+        },
+        1,
+    );
+    system.run_next_block();
+
+    let challenge = query_challenge(&program, 0);
+    run_until(&system, challenge.start_time);
+    for _ in 0..2 {
+        program.send(USER, Command::CompleteDaily { id: 0 });
+        program.send(other_user, Command::CompleteDaily { id: 0 });
+    }
+    let block_run_result = system.run_next_block();
+    let log = block_run_result.decoded_log::<Result<Event, Error>>();
+    assert!(log.iter().any(|entry| matches!(
+        entry.payload(),
+        Ok(Event::ChallengeTaskCompleted {
+            id: 0,
+            day: 0,
+            tasks: 2
+        })
+    )));
+
+    let participant = query_participants(&program, 0)[0].clone();
+    assert_eq!(participant.day_progress, BTreeMap::from([(0, 2)]));
+    assert!(participant.completed_days.is_empty());
+
+    program.send(USER, Command::CompleteDaily { id: 0 });
+    let block_run_result = system.run_next_block();
+    let log = block_run_result.decoded_log::<Result<Event, Error>>();
+    assert!(matches!(
+        log[0].payload(),
+        Ok(Event::ChallengeDailyCompleted { id: 0 })
+    ));
+
+    let participant = query_participants(&program, 0)[0].clone();
+    assert!(participant.day_progress.is_empty());
+    assert_eq!(participant.completed_days.to_vec(), vec![0]);
+
+    // the unmet first day keeps its count while the next one is started
+    run_until(&system, challenge.start_time + 86400);
+    for _ in 0..3 {
+        program.send(USER, Command::CompleteDaily { id: 0 });
+    }
+    program.send(other_user, Command::CompleteDaily { id: 0 });
+    system.run_next_block();
+
+    let participants = query_participants(&program, 0);
+    assert_eq!(participants[0].completed_days.to_vec(), vec![0, 1]);
+    assert_eq!(
+        participants[1].day_progress,
+        BTreeMap::from([(0, 2), (1, 1)])
+    );
+
+    run_until(&system, challenge.end_time);
+    system.run_next_block();
+
+    let challenge = query_challenge(&program, 0);
+    assert_eq!(challenge.status, ChallengeStatus::Completed);
+    assert_eq!(challenge.finalization.unwrap().succeeded_count, 1);
+}
+
+#[test]
+fn test_sub_hour_timezone_challenge() {
+    let system = setup_system();
+    let program = setup_program(&system);
+
+    for (timezone, valid) in [
+        (UtcOffset::Minutes(330), true),
+        (UtcOffset::Minutes(840), true),
+        (UtcOffset::Minutes(310), false),
+        (UtcOffset::Hours(15), false),
+    ] {
+        let now = system.block_timestamp() / 1000;
+        let start_time = calculate_future_day_start(now, timezone.minutes(), 1);
+        let end_time = calculate_future_day_start(now, timezone.minutes(), 2);
+        let mut command = challenge_command(&system, 1, 1);
+        if let Command::CreateChallenge {
+            timezone: offset,
+            start_time: start,
+            end_time: end,
+            ..
+        } = &mut command
+        {
+            *offset = timezone;
+            *start = start_time;
+            *end = end_time;
+        }
+
+        program.send(USER, command);
+        let block_run_result = system.run_next_block();
+        let log = block_run_result.decoded_log::<Result<Event, Error>>();
+        if valid {
+            assert!(log[0].payload().is_ok());
+        } else {
+            assert!(matches!(
+                log[0].payload(),
+                Err(Error::InvalidTimezone { timezone: minutes }) if *minutes == timezone.minutes()
+            ));
+        }
+    }
+
+    let challenge = query_challenge(&program, 0);
+    assert_eq!(challenge.timezone, 330);
+    assert_eq!((challenge.start_time as i64 + 330 * 60) % 86400, 0);
+}
+
+#[test]
+fn test_participants_follow_their_own_timezone() {
+    let system = setup_system();
+    let program = setup_program(&system);
+    let other_user = 4;
+    system.mint_to(other_user, constants::EXISTENTIAL_DEPOSIT * 1000);
+    setup_challenge(&system, &program, 1, 1);
+
+    program.send_with_value(
+        other_user,
+        Command::JoinChallenge {
+            id: 0,
+            team: None,
+            timezone: Some(UtcOffset::Minutes(7)),
+        },
+        1,
+    );
+    let block_run_result = system.run_next_block();
+    let log = block_run_result.decoded_log::<Result<Event, Error>>();
+    assert!(matches!(
+        log[0].payload(),
+        Err(Error::InvalidTimezone { timezone: 7 })
+    ));
+
+    program.send_with_value(
+        USER,
+        Command::JoinChallenge {
+            id: 0,
+            team: None,
+            timezone: None,
+        },
+        1,
+    );
+    program.send_with_value(
+        other_user,
+        Command::JoinChallenge {
+            id: 0,
+            team: None,
+            timezone: Some(UtcOffset::Hours(-4)),
+        },
+        1,
+    );
+    system.run_next_block();
+
+    // midnight in UTC+8 is noon in UTC-4, whose own day starts 12 hours later
+    let challenge = query_challenge(&program, 0);
+    let shifted_start = challenge.start_time + 12 * 3600;
+    let participants = query_participants(&program, 0);
+    assert_eq!(participants[0].start_time, challenge.start_time);
+    assert_eq!(participants[1].timezone, -240);
+    assert_eq!(participants[1].start_time, shifted_start);
+
+    run_until(&system, challenge.start_time);
+    program.send(USER, Command::CompleteDaily { id: 0 });
+    program.send(other_user, Command::CompleteDaily { id: 0 });
+    let block_run_result = system.run_next_block();
+    let log = block_run_result.decoded_log::<Result<Event, Error>>();
+    assert!(log.iter().any(|entry| matches!(
+        entry.payload(),
+        Err(Error::CheckInNotOpen { id: 0, opens_at }) if *opens_at == shifted_start
+    )));
+
+    let challenge = query_challenge(&program, 0);
+    assert_eq!(challenge.status, ChallengeStatus::Executing);
+    assert_eq!(challenge.execution_end_time, challenge.end_time + 12 * 3600);
+
+    run_until(&system, challenge.end_time);
+    program.send(USER, Command::CompleteDaily { id: 0 });
+    program.send(other_user, Command::CompleteDaily { id: 0 });
+    let block_run_result = system.run_next_block();
+    let log = block_run_result.decoded_log::<Result<Event, Error>>();
+    assert!(log.iter().any(|entry| matches!(
+        entry.payload(),
+        Err(Error::NotACheckInDay { id: 0, day: 1 })
+    )));
+    assert!(log.iter().any(|entry| matches!(
+        entry.payload(),
+        Ok(Event::ChallengeDailyCompleted { id: 0 })
+    )));
+
+    run_until(&system, challenge.execution_end_time);
+    system.run_next_block();
+
+    let challenge = query_challenge(&program, 0);
+    assert_eq!(challenge.status, ChallengeStatus::Completed);
+    assert_eq!(challenge.finalization.unwrap().succeeded_count, 2);
+}
+
+#[test]
+fn test_weekdays_follow_the_challenge_timezone() {
+    let system = setup_system();
+    let program = setup_program(&system);
+    let other_user = 4;
+    system.mint_to(other_user, constants::EXISTENTIAL_DEPOSIT * 1000);
+
+    // a two-day challenge over Friday and Saturday in UTC-12
+    let now = system.block_timestamp() / 1000;
+    let mut start_time = calculate_future_day_start(now, -720, 1);
+    while weekday(start_time, -720) != 4 {
+        start_time += 86400;
+    }
+    let mut command = challenge_command(&system, 1, 2);
+    if let Command::CreateChallenge {
+        timezone,
+        start_time: start,
+        end_time: end,
+        cadence,
+        ..
+    } = &mut command
+    {
+        *timezone = UtcOffset::Hours(-12);
+        *start = start_time;
+        *end = start_time + 2 * 86400;
+        *cadence = Cadence::Weekdays;
+    }
+    submit_challenge(&system, &program, command);
+
+    program.send_with_value(
+        USER,
+        Command::JoinChallenge {
+            id: 0,
+            team: None,
+            timezone: None,
+        },
+        1,
+    );
+    program.send_with_value(
+        other_user,
+        Command::JoinChallenge {
+            id: 0,
+            team: None,
+            timezone: Some(UtcOffset::Minutes(840)),
+        },
+        1,
+    );
+    system.run_next_block();
+
+    // the first midnight in UTC+14 is already Sunday there, but still Friday
+    // in the challenge timezone, so it is a check-in day and Monday is not
+    let participants = query_participants(&program, 0);
+    let shifted_start = participants[1].start_time;
+    assert_eq!(weekday(shifted_start, 840), 6);
+    assert_eq!(weekday(shifted_start, -720), 4);
+
+    run_until(&system, start_time);
+    program.send(USER, Command::CompleteDaily { id: 0 });
+    system.run_next_block();
+
+    run_until(&system, shifted_start);
+    program.send(other_user, Command::CompleteDaily { id: 0 });
+    let block_run_result = system.run_next_block();
+    let log = block_run_result.decoded_log::<Result<Event, Error>>();
+    assert!(log.iter().any(|entry| matches!(
+        entry.payload(),
+        Ok(Event::ChallengeDailyCompleted { id: 0 })
+    )));
+
+    run_until(&system, shifted_start + 86400);
+    program.send(other_user, Command::CompleteDaily { id: 0 });
+    let block_run_result = system.run_next_block();
+    let log = block_run_result.decoded_log::<Result<Event, Error>>();
+    assert!(log.iter().any(|entry| matches!(
+        entry.payload(),
+        Err(Error::NotACheckInDay { id: 0, day: 1 })
+    )));
+
+    let challenge = query_challenge(&program, 0);
+    run_until(&system, challenge.execution_end_time);
+    system.run_next_block();
+
+    let challenge = query_challenge(&program, 0);
+    assert_eq!(challenge.status, ChallengeStatus::Completed);
+    assert_eq!(challenge.finalization.unwrap().succeeded_count, 2);
+}
+
+#[test]
+fn test_late_join() {
+    let system = setup_system();
+    let program = setup_program(&system);
+    let (late_user, too_late_user) = (4, 5);
+    system.mint_to(late_user, constants::EXISTENTIAL_DEPOSIT * 1000);
+    system.mint_to(too_late_user, constants::EXISTENTIAL_DEPOSIT * 1000);
+
+    let mut command = challenge_command(&system, 1, 4);
+    if let Command::CreateChallenge {
+        entry_fee,
+        late_join,
+        ..
+    } = &mut command
+    {
+        *entry_fee = 6;
+        *late_join = Some(LateJoin {
+            days: 2,
+            premium: 2,
+        });
+    }
+    submit_challenge(&system, &program, command);
+    let join = |account, value| {
+        program.send_with_value(
+            account,
+            Command::JoinChallenge {
+                id: 0,
+                team: None,
+                timezone: None,
+            },
+            value,
+        );
+        let block_run_result = system.run_next_block();
+        block_run_result.decoded_log::<Result<Event, Error>>()
+    };
+    assert!(join(USER, 6)[0].payload().is_ok());
+
+    let challenge = query_challenge(&program, 0);
+    run_until(&system, challenge.start_time + 3600);
+    assert!(matches!(
+        join(late_user, 6)[0].payload(),
+        Err(Error::NotEnoughFunds {
+            expected: 8,
+            actual: 6
+        })
+    ));
+    assert!(join(late_user, 8)[0].payload().is_ok());
+
+    // the late joiner's days start at the next midnight
+    let second_day = challenge.start_time + 86400;
+    program.send(late_user, Command::CompleteDaily { id: 0 });
+    let block_run_result = system.run_next_block();
+    let log = block_run_result.decoded_log::<Result<Event, Error>>();
+    assert!(matches!(
+        log[0].payload(),
+        Err(Error::CheckInNotOpen { id: 0, opens_at }) if *opens_at == second_day
+    ));
+    let late_participant = query_participants(&program, 0)[1].clone();
+    assert_eq!(late_participant.start_time, second_day);
+    assert_eq!(late_participant.end_time, challenge.end_time);
+
+    for day in 0..4 {
+        run_until(&system, challenge.start_time + day * 86400 + 3600);
+        program.send(USER, Command::CompleteDaily { id: 0 });
+        if day > 0 {
+            program.send(late_user, Command::CompleteDaily { id: 0 });
+        }
+        system.run_next_block();
+        if day == 2 {
+            assert!(matches!(
+                join(too_late_user, 8)[0].payload(),
+                Err(Error::LateJoinClosed { id: 0 })
+            ));
+        }
+    }
+
+    run_until(&system, challenge.end_time);
+    system.run_next_block();
+
+    // both stakes weigh 6: the late one is 8 for three days out of four
+    let challenge = query_challenge(&program, 0);
+    assert_eq!(challenge.status, ChallengeStatus::Completed);
+    let finalization = challenge.finalization.unwrap();
+    assert_eq!(finalization.succeeded_count, 2);
+    assert_eq!(finalization.total_succeeded_weight, 12);
+    assert_eq!(finalization.total_paid, 14);
+}
+
+#[test]
+fn test_challenge_summaries_and_participant_pages() {
+    let system = setup_system();
+    let program = setup_program(&system);
+    let accounts = [USER, 4, 5];
+    for account in &accounts[1..] {
+        system.mint_to(*account, constants::EXISTENTIAL_DEPOSIT * 1000);
+    }
+    setup_challenge(&system, &program, 1, 1);
+
+    for account in accounts {
+        program.send_with_value(
+            account,
+            Command::JoinChallenge {
+                id: 0,
+                team: None,
+                timezone: None,
+            },
+            1,
+        );
+    }
+    program.send_with_value(4, Command::SponsorChallenge { id: 0, amount: 0 }, 2);
+    system.run_next_block();
+
+    let state_reply: StateReply = program
+        .read_state(StateQuery::QueryChallenges {
+            filters: vec![ChallengeFilter::All],
+            include_recruiting: true,
+            include_recruit_failed: false,
+            include_executing: false,
+            include_finalizing: false,
+            include_completed: false,
+            sort: ChallengeSort::Id,
+            order: SortOrder::Ascending,
+            offset: 0,
+            count: 10,
+        })
+        .unwrap();
+    let StateReply::QueryChallenges { challenges, total } = state_reply else {
+        panic!("Expected QueryChallenges variant");
+    };
+    assert_eq!(total, 1);
+    assert_eq!(challenges.len(), 1);
+    assert_eq!(challenges[0].participant_count, 3);
+    assert_eq!(challenges[0].sponsor_count, 1);
+    assert_eq!(challenges[0].prize_pool, 5);
+    assert_eq!(challenges[0].creator, ActorId::from(USER));
+
+    let state_reply: StateReply = program
+        .read_state(StateQuery::QueryParticipants {
+            id: 0,
+            offset: 1,
+            count: 10,
+        })
+        .unwrap();
+    let StateReply::QueryParticipants { participants } = state_reply else {
+        panic!("Expected QueryParticipants variant");
+    };
+    let ids: Vec<ActorId> = participants
+        .iter()
+        .map(|participant| participant.id)
+        .collect();
+    assert_eq!(ids, vec![ActorId::from(4), ActorId::from(5)]);
+
+    for (account, joined) in [(5, true), (6, false)] {
+        let state_reply: StateReply = program
+            .read_state(StateQuery::QueryParticipant {
+                id: 0,
+                account: account.into(),
+            })
+            .unwrap();
+        let StateReply::QueryParticipant { participant } = state_reply else {
+            panic!("Expected QueryParticipant variant");
+        };
+        assert_eq!(participant.is_some(), joined);
+    }
+
+    let state_reply: StateReply = program
+        .read_state(StateQuery::QuerySponsors {
+            id: 0,
+            offset: 0,
+            count: 10,
+        })
+        .unwrap();
+    let StateReply::QuerySponsors { sponsors } = state_reply else {
+        panic!("Expected QuerySponsors variant");
+    };
+    assert_eq!(sponsors.len(), 1);
+    assert_eq!(sponsors[0].sponsor, ActorId::from(4));
+    assert_eq!(sponsors[0].payment, 2);
+}
+
+#[test]
+fn test_unknown_ids_and_bad_payloads_return_errors() {
+    let system = setup_system();
+    let program = setup_program(&system);
+    setup_challenge(&system, &program, 1, 1);
+
+    for query in [
+        StateQuery::QueryChallenge { id: 7 },
+        StateQuery::QueryParticipants {
+            id: 7,
+            offset: 0,
+            count: 10,
+        },
+        StateQuery::QueryParticipant {
+            id: 7,
+            account: USER.into(),
+        },
+        StateQuery::QuerySponsors {
+            id: 7,
+            offset: 0,
+            count: 10,
+        },
+    ] {
+        let state_reply: StateReply = program.read_state(query).unwrap();
+        assert!(matches!(
+            state_reply,
+            StateReply::Error {
+                error: Error::ChallengeNotFound { id: 7 }
+            }
+        ));
+    }
+
+    for query in [
+        StateQuery::QuerySeries { id: 3 },
+        StateQuery::QuerySeriesChallenges {
+            id: 3,
+            offset: 0,
+            count: 10,
+        },
+    ] {
+        let state_reply: StateReply = program.read_state(query).unwrap();
+        assert!(matches!(
+            state_reply,
+            StateReply::Error {
+                error: Error::SeriesNotFound { id: 3 }
+            }
+        ));
+    }
+
+    let state_reply: StateReply = program
+        .read_state(StateQuery::QueryBadge { id: 0 })
+        .unwrap();
+    assert!(matches!(
+        state_reply,
+        StateReply::Error {
+            error: Error::BadgeNotFound { id: 0 }
+        }
+    ));
+
+    let bytes = program.read_state_bytes(vec![0xff, 0xff]).unwrap();
+    let state_reply = StateReply::decode(&mut bytes.as_slice()).unwrap();
+    assert!(matches!(
+        state_reply,
+        StateReply::Error {
+            error: Error::DecodeFailed
+        }
+    ));
+
+    let commands = [
+        Command::JoinChallenge {
+            id: 7,
+            team: None,
+            timezone: None,
+        },
+        Command::SponsorChallenge { id: 7, amount: 0 },
+        Command::CompleteDaily { id: 7 },
+    ];
+    for command in commands {
+        program.send_with_value(USER, command, 1);
+        let log = system
+            .run_next_block()
+            .decoded_log::<Result<Event, Error>>();
+        assert!(matches!(
+            log[0].payload(),
+            Err(Error::ChallengeNotFound { id: 7 })
+        ));
+    }
+
+    for command in [
+        Command::EnrollInSeries { id: 3 },
+        Command::LeaveSeries { id: 3 },
+    ] {
+        program.send(USER, command);
+        let log = system
+            .run_next_block()
+            .decoded_log::<Result<Event, Error>>();
+        assert!(matches!(
+            log[0].payload(),
+            Err(Error::SeriesNotFound { id: 3 })
+        ));
+    }
+
+    program.send_bytes(USER, [0xff]);
+    let log = system
+        .run_next_block()
+        .decoded_log::<Result<Event, Error>>();
+    assert!(matches!(log[0].payload(), Err(Error::DecodeFailed)));
+}
+
+#[test]
+fn test_challenges_are_filtered_and_sorted() {
+    let system = setup_system();
+    let program = setup_program(&system);
+    system.mint_to(4, constants::EXISTENTIAL_DEPOSIT * 1000);
+
+    let editions = [
+        ("Morning Run", 1, 1, 3),
+        ("Evening run", 5, 2, 7),
+        ("Reading", 3, 1, 7),
+        ("Swim", 2, 3, 2),
+    ];
+    let mut start_times = Vec::new();
+    for (id, (title, fee, recruit_duration, execute_duration)) in editions.into_iter().enumerate() {
+        let mut command = challenge_command(&system, recruit_duration, execute_duration);
+        if let Command::CreateChallenge {
+            name,
+            entry_fee,
+            start_time,
+            teams,
+            ..
+        } = &mut command
+        {
+            *name = title.to_string();
+            *entry_fee = fee;
+            start_times.push(*start_time);
+            if id == 2 {
+                for team in ["red", "blue"] {
+                    teams.push(TeamConfig {
+                        name: team.to_string(),
+                        captain: None,
+                        max_members: Some(1),
+                    });
+                }
+            }
+        }
+        submit_challenge(&system, &program, command);
+    }
+
+    for (account, id, team, fee) in [
+        (USER, 0, None, 1),
+        (4, 0, None, 1),
+        (USER, 2, Some(0), 3),
+        (4, 2, Some(1), 3),
+    ] {
+        program.send_with_value(
+            account,
+            Command::JoinChallenge {
+                id,
+                team,
+                timezone: None,
+            },
+            fee,
+        );
+    }
+    program.send_with_value(USER, Command::SponsorChallenge { id: 1, amount: 0 }, 10);
+    system.run_next_block();
+
+    let query = |filters: Vec<ChallengeFilter>, sort, order, offset| {
+        let state_reply: StateReply = program
+            .read_state(StateQuery::QueryChallenges {
+                filters,
+                include_recruiting: true,
+                include_recruit_failed: false,
+                include_executing: false,
+                include_finalizing: false,
+                include_completed: false,
+                sort,
+                order,
+                offset,
+                count: 2,
+            })
+            .unwrap();
+        let StateReply::QueryChallenges { challenges, total } = state_reply else {
+            panic!("Expected QueryChallenges variant");
+        };
+        let ids: Vec<u64> = challenges.iter().map(|challenge| challenge.id).collect();
+        (ids, total)
+    };
+    let filtered = |filters| query(filters, ChallengeSort::Id, SortOrder::Ascending, 0);
+
+    assert_eq!(
+        filtered(vec![ChallengeFilter::NameContains("RUN".to_string())]),
+        (vec![0, 1], 2)
+    );
+    assert_eq!(
+        filtered(vec![ChallengeFilter::EntryFee { min: 2, max: 5 }]),
+        (vec![1, 2], 3)
+    );
+    assert_eq!(
+        filtered(vec![ChallengeFilter::StartTime {
+            from: start_times[0],
+            to: start_times[0],
+        }]),
+        (vec![0, 2], 2)
+    );
+    assert_eq!(
+        filtered(vec![ChallengeFilter::Duration {
+            min_days: 5,
+            max_days: 7,
+        }]),
+        (vec![1, 2], 2)
+    );
+    assert_eq!(filtered(vec![ChallengeFilter::Timezone(0)]), (vec![], 0));
+    assert_eq!(filtered(vec![ChallengeFilter::Open]), (vec![0, 1], 3));
+    assert_eq!(
+        filtered(vec![
+            ChallengeFilter::Joined(USER.into()),
+            ChallengeFilter::Open
+        ]),
+        (vec![0], 1)
+    );
+    assert_eq!(
+        filtered(vec![
+            ChallengeFilter::Sponsored(USER.into()),
+            ChallengeFilter::Timezone(480)
+        ]),
+        (vec![1], 1)
+    );
+
+    assert_eq!(
+        query(
+            Vec::new(),
+            ChallengeSort::PrizePool,
+            SortOrder::Descending,
+            0
+        ),
+        (vec![1, 2], 4)
+    );
+    assert_eq!(
+        query(
+            Vec::new(),
+            ChallengeSort::PrizePool,
+            SortOrder::Descending,
+            2
+        ),
+        (vec![0, 3], 4)
+    );
+    assert_eq!(
+        query(
+            Vec::new(),
+            ChallengeSort::ParticipantCount,
+            SortOrder::Ascending,
+            0
+        ),
+        (vec![1, 3], 4)
+    );
+    assert_eq!(
+        query(
+            Vec::new(),
+            ChallengeSort::StartTime,
+            SortOrder::Descending,
+            0
+        ),
+        (vec![3, 1], 4)
+    );
+}
+
+/// Filters the challenges one by one, the way `QueryChallenges` did before it
+/// had indexes.
+fn naive_query(
+    challenges: &[Challenge],
+    filters: &[ChallengeFilter],
+    statuses: &[ChallengeStatus],
+    sort: ChallengeSort,
+    order: SortOrder,
+) -> Vec<u64> {
+    let matches = |challenge: &Challenge, filter: &ChallengeFilter| match filter {
+        ChallengeFilter::All => true,
+        ChallengeFilter::Created(account) => challenge.creator == *account,
+        ChallengeFilter::Joined(account) => challenge
+            .participants
+            .iter()
+            .any(|participant| participant.id == *account),
+        ChallengeFilter::Sponsored(account) => challenge
+            .sponsors
+            .iter()
+            .any(|sponsorship| sponsorship.sponsor == *account),
+        ChallengeFilter::NameContains(text) => {
+            challenge.name.to_lowercase().contains(&text.to_lowercase())
+        }
+        ChallengeFilter::EntryFee { min, max } => {
+            *min <= challenge.entry_fee && challenge.entry_fee <= *max
+        }
+        ChallengeFilter::StartTime { from, to } => {
+            *from <= challenge.start_time && challenge.start_time <= *to
+        }
+        ChallengeFilter::Timezone(timezone) => challenge.timezone == *timezone,
+        ChallengeFilter::Duration { min_days, max_days } => {
+            let days = (challenge.end_time - challenge.start_time) / 86400;
+            *min_days as u64 <= days && days <= *max_days as u64
+        }
+        // none of the challenges has teams or a late join window
+        ChallengeFilter::Open => challenge.status == ChallengeStatus::Recruiting,
+    };
+
+    let mut found: Vec<&Challenge> = challenges
+        .iter()
+        .filter(|challenge| statuses.contains(&challenge.status))
+        .filter(|challenge| filters.iter().all(|filter| matches(challenge, filter)))
+        .collect();
+    let key = |challenge: &Challenge| match sort {
+        ChallengeSort::Id => challenge.id as u128,
+        ChallengeSort::PrizePool => challenge.prize_pool,
+        ChallengeSort::StartTime => challenge.start_time as u128,
+        ChallengeSort::ParticipantCount => challenge.participants.len() as u128,
+    };
+    found.sort_by_key(|challenge| {
+        let key = match order {
+            SortOrder::Ascending => key(challenge) as i128,
+            SortOrder::Descending => -(key(challenge) as i128),
+        };
+        (key, challenge.id)
+    });
+    found.iter().map(|challenge| challenge.id).collect()
+}
+
+#[test]
+fn test_indexed_queries_match_a_full_scan() {
+    let system = setup_system();
+    let program = setup_program(&system);
+    for account in [4, 5] {
+        system.mint_to(account, constants::EXISTENTIAL_DEPOSIT * 1000);
+    }
+
+    let names = ["Run", "Read", "Swim"];
+    for i in 0..8u32 {
+        let mut command = challenge_command(&system, 1 + i % 3, 1 + i % 4);
+        if let Command::CreateChallenge {
+            name, entry_fee, ..
+        } = &mut command
+        {
+            *name = format!("{} {i}", names[i as usize % 3]);
+            *entry_fee = 1 + i as u128 % 3;
+        }
+        let creator = if i % 3 == 2 { 4 } else { USER };
+        program.send(creator, command);
+        system.run_next_block();
+    }
+
+    for i in 0..8u64 {
+        let fee = 1 + i as u128 % 3;
+        let mut joiners = Vec::new();
+        if i % 2 == 0 {
+            joiners.push(USER);
+        }
+        if i % 4 == 0 {
+            joiners.push(4);
+        }
+        for account in joiners {
+            program.send_with_value(
+                account,
+                Command::JoinChallenge {
+                    id: i,
+                    team: None,
+                    timezone: None,
+                },
+                fee,
+            );
+        }
+    }
+    // the sponsorships of the same challenge are not made one after another
+    for _ in 0..2 {
+        for id in [1, 5] {
+            program.send_with_value(5, Command::SponsorChallenge { id, amount: 0 }, 3);
+        }
+    }
+    system.run_next_block();
+
+    // the first challenge ends while later ones still recruit or run
+    let first = query_challenge(&program, 0);
+    run_until(&system, first.execution_end_time + 600);
+    for _ in 0..20 {
+        system.run_next_block();
+    }
+
+    let challenges: Vec<Challenge> = (0..8)
+        .map(|id| query_full_challenge(&program, id))
+        .collect();
+    let mut statuses: Vec<ChallengeStatus> = challenges
+        .iter()
+        .map(|challenge| challenge.status)
+        .collect();
+    statuses.sort();
+    statuses.dedup();
+    assert!(statuses.len() >= 3, "{statuses:?}");
+
+    let (first_start, last_start) = (challenges[0].start_time, challenges[1].start_time);
+    let filter_sets = vec![
+        vec![],
+        vec![ChallengeFilter::All],
+        vec![ChallengeFilter::Created(USER.into())],
+        vec![ChallengeFilter::Joined(USER.into())],
+        vec![ChallengeFilter::Joined(4.into()), ChallengeFilter::Open],
+        vec![ChallengeFilter::Sponsored(5.into())],
+        vec![ChallengeFilter::NameContains("r".to_string())],
+        vec![ChallengeFilter::EntryFee { min: 2, max: 3 }],
+        vec![ChallengeFilter::StartTime {
+            from: first_start,
+            to: last_start,
+        }],
+        vec![
+            ChallengeFilter::StartTime {
+                from: first_start,
+                to: first_start,
+            },
+            ChallengeFilter::Joined(USER.into()),
+        ],
+        vec![ChallengeFilter::Duration {
+            min_days: 2,
+            max_days: 3,
+        }],
+        vec![ChallengeFilter::Timezone(480)],
+    ];
+    let status_sets = [
+        vec![
+            ChallengeStatus::Recruiting,
+            ChallengeStatus::RecruitFailed,
+            ChallengeStatus::Executing,
+            ChallengeStatus::Finalizing,
+            ChallengeStatus::Completed,
+        ],
+        vec![ChallengeStatus::Recruiting],
+        vec![ChallengeStatus::Executing, ChallengeStatus::Completed],
+        vec![ChallengeStatus::RecruitFailed, ChallengeStatus::Recruiting],
+        vec![],
+    ];
+    let sorts = [
+        ChallengeSort::Id,
+        ChallengeSort::PrizePool,
+        ChallengeSort::StartTime,
+        ChallengeSort::ParticipantCount,
+    ];
+
+    // every filter set against every status set, cycling through the sorts to
+    // keep the number of queries down
+    let orders = [SortOrder::Ascending, SortOrder::Descending];
+    let combinations = filter_sets
+        .iter()
+        .flat_map(|filters| status_sets.iter().map(move |included| (filters, included)));
+    for (step, (filters, included)) in combinations.enumerate() {
+        let (sort, order) = (sorts[step % 4], orders[step / 4 % 2]);
+        let expected = naive_query(&challenges, filters, included, sort, order);
+        let state_reply: StateReply = program
+            .read_state(StateQuery::QueryChallenges {
+                filters: filters.clone(),
+                include_recruiting: included.contains(&ChallengeStatus::Recruiting),
+                include_recruit_failed: included.contains(&ChallengeStatus::RecruitFailed),
+                include_executing: included.contains(&ChallengeStatus::Executing),
+                include_finalizing: included.contains(&ChallengeStatus::Finalizing),
+                include_completed: included.contains(&ChallengeStatus::Completed),
+                sort,
+                order,
+                offset: 1,
+                count: 3,
+            })
+            .unwrap();
+        let StateReply::QueryChallenges { challenges, total } = state_reply else {
+            panic!("Expected QueryChallenges variant");
+        };
+        let ids: Vec<u64> = challenges.iter().map(|challenge| challenge.id).collect();
+        let context = format!("{filters:?} {included:?} {sort:?} {order:?}");
+        assert_eq!(total, expected.len() as u64, "{context}");
+        assert_eq!(
+            ids,
+            expected.into_iter().skip(1).take(3).collect::<Vec<_>>(),
+            "{context}"
+        );
+    }
+}
+
+#[test]
+fn test_finished_challenges_are_archived_and_claims_kept() {
+    let system = setup_system();
+    let program = setup_program(&system);
+    system.mint_to(4, constants::EXISTENTIAL_DEPOSIT * 1000);
+
+    let balances = Rc::new(RefCell::new(BTreeMap::from([(ActorId::from(USER), 100)])));
+    let token = Program::mock_with_id(
+        &system,
+        TOKEN,
+        MockToken {
+            owner: program.id(),
+            balances: balances.clone(),
+        },
+    );
+    token.send_bytes(USER, []);
+    system.run_next_block();
+
+    let mut command = challenge_command(&system, 1, 1);
+    if let Command::CreateChallenge {
+        entry_fee,
+        currency,
+        ..
+    } = &mut command
+    {
+        *entry_fee = 10;
+        *currency = Some(token.id());
+    }
+    submit_challenge(&system, &program, command);
+    setup_challenge(&system, &program, 1, 1);
+
+    program.send(
+        USER,
+        Command::JoinChallenge {
+            id: 0,
+            team: None,
+            timezone: None,
+        },
+    );
+    program.send_with_value(4, Command::SponsorChallenge { id: 1, amount: 0 }, 5);
+    system.run_next_block();
+    system.run_next_block();
+
+    let challenge = query_challenge(&program, 0);
+    run_until(&system, challenge.start_time);
+    program.send(USER, Command::CompleteDaily { id: 0 });
+    // the prize transfer bounces, leaving the prize to be claimed
+    balances.borrow_mut().insert(program.id(), 0);
+    run_until(&system, challenge.end_time);
+    system.run_next_block();
+    system.run_next_block();
+
+    let record = query_full_challenge(&program, 0);
+    assert_eq!(record.status, ChallengeStatus::Completed);
+    assert_eq!(
+        query_challenge(&program, 1).status,
+        ChallengeStatus::RecruitFailed
+    );
+    assert_eq!(query_payouts(&program, PayoutStatus::Failed).len(), 1);
+
+    let archive = |count| {
+        program.send(USER, Command::ArchiveChallenges { count });
+        let log = system
+            .run_next_block()
+            .decoded_log::<Result<Event, Error>>();
+        match log[0].payload() {
+            Ok(Event::ChallengesArchived { ids }) => ids.clone(),
+            payload => panic!("Unexpected reply {payload:?}"),
+        }
+    };
+    assert!(archive(10).is_empty());
+
+    let StateReply::QueryChallengeLog {
+        entries: mut history,
+        ..
+    } = program
+        .read_state(StateQuery::QueryChallengeLog {
+            id: 0,
+            offset: 0,
+            count: 100,
+        })
+        .unwrap()
+    else {
+        panic!("Expected QueryChallengeLog variant");
+    };
+    assert_eq!(history[0].action, LogAction::Created);
+
+    run_until(&system, record.execution_end_time + ARCHIVE_RETENTION);
+    assert_eq!(archive(1), vec![0]);
+    history.push(LogEntry {
+        block: system.block_height(),
+        timestamp: system.block_timestamp() / 1000,
+        actor: ActorId::from(program.id().into_bytes()),
+        action: LogAction::Archived,
+        amount: 0,
+    });
+    assert_eq!(archive(10), vec![1]);
+    assert!(archive(10).is_empty());
+
+    // archived challenges are dropped from the accounts' lists, but whoever
+    // took part still counts as a participant
+    let state_reply: StateReply = program.read_state(StateQuery::QueryGlobalStats).unwrap();
+    let StateReply::QueryGlobalStats { stats } = state_reply else {
+        panic!("Expected QueryGlobalStats variant");
+    };
+    assert_eq!(stats.unique_participants, 1);
+
+    let state_reply: StateReply = program
+        .read_state(StateQuery::QueryChallenge { id: 0 })
+        .unwrap();
+    assert!(matches!(
+        state_reply,
+        StateReply::Error {
+            error: Error::ChallengeArchived { id: 0 }
+        }
+    ));
+    program.send_with_value(
+        4,
+        Command::JoinChallenge {
+            id: 1,
+            team: None,
+            timezone: None,
+        },
+        1,
+    );
+    let log = system
+        .run_next_block()
+        .decoded_log::<Result<Event, Error>>();
+    assert!(matches!(
+        log[0].payload(),
+        Err(Error::ChallengeArchived { id: 1 })
+    ));
+
+    let state_reply: StateReply = program
+        .read_state(StateQuery::QueryArchivedChallenge { id: 0 })
+        .unwrap();
+    let StateReply::QueryArchivedChallenge { archive } = state_reply else {
+        panic!("Expected QueryArchivedChallenge variant");
+    };
+    assert_eq!(archive.summary.status, ChallengeStatus::Completed);
+    assert_eq!(archive.summary.participant_count, 1);
+    assert_eq!(archive.winner_count, 1);
+    assert_eq!(archive.total_paid, 10);
+    // the log of an archived challenge is only kept in its hash
+    assert_eq!(archive.record_hash, record_hash(&record, &history));
+    let state_reply: StateReply = program
+        .read_state(StateQuery::QueryChallengeLog {
+            id: 0,
+            offset: 0,
+            count: 100,
+        })
+        .unwrap();
+    assert!(matches!(
+        state_reply,
+        StateReply::Error {
+            error: Error::ChallengeArchived { id: 0 }
+        }
+    ));
+
+    let state_reply: StateReply = program
+        .read_state(StateQuery::QueryArchivedChallenges {
+            offset: 1,
+            count: 10,
+        })
+        .unwrap();
+    let StateReply::QueryArchivedChallenges { archives } = state_reply else {
+        panic!("Expected QueryArchivedChallenges variant");
+    };
+    assert_eq!(archives.len(), 1);
+    assert_eq!(archives[0].summary.id, 1);
+    assert_eq!(archives[0].winner_count, 0);
+    assert_eq!(archives[0].total_paid, 5);
+
+    let state_reply: StateReply = program.read_state(StateQuery::TotalChallengeCount).unwrap();
+    assert!(matches!(
+        state_reply,
+        StateReply::TotalChallengeCount { count: 2 }
+    ));
+
+    // the bounced prize can still be claimed
+    let state_reply: StateReply = program
+        .read_state(StateQuery::QueryClaimableBalance {
+            account: USER.into(),
+            currency: Some(token.id()),
+        })
+        .unwrap();
+    assert!(matches!(
+        state_reply,
+        StateReply::QueryClaimableBalance { amount: 10 }
+    ));
+    balances.borrow_mut().insert(program.id(), 10);
+    program.send(
+        USER,
+        Command::ClaimBalance {
+            currency: Some(token.id()),
+        },
+    );
+    system.run_next_block();
+    system.run_next_block();
+    assert_eq!(balances.borrow()[&ActorId::from(USER)], 100);
+}
+
+#[test]
+fn test_challenge_analytics() {
+    let system = setup_system();
+    let program = setup_program(&system);
+    let mut command = challenge_command(&system, 1, 3);
+    if let Command::CreateChallenge { entry_fee, .. } = &mut command {
+        *entry_fee = 30;
+    }
+    submit_challenge(&system, &program, command);
+
+    let users: Vec<u64> = (100..104).collect();
+    for &user in &users {
+        system.mint_to(user, constants::EXISTENTIAL_DEPOSIT * 10);
+        program.send_with_value(
+            user,
+            Command::JoinChallenge {
+                id: 0,
+                team: None,
+                timezone: None,
+            },
+            30,
+        );
+    }
+    system.run_next_block();
+
+    let analytics = |id| -> Result<ChallengeAnalytics, Error> {
+        match program
+            .read_state(StateQuery::QueryChallengeAnalytics { id })
+            .unwrap()
+        {
+            StateReply::QueryChallengeAnalytics { analytics } => Ok(analytics),
+            StateReply::Error { error } => Err(error),
+            _ => panic!("Expected QueryChallengeAnalytics variant"),
+        }
+    };
+    let progress = |offset, count| -> Vec<ParticipantProgress> {
+        match program
+            .read_state(StateQuery::QueryParticipantsProgress {
+                id: 0,
+                offset,
+                count,
+            })
+            .unwrap()
+        {
+            StateReply::QueryParticipantsProgress { progress } => progress,
+            _ => panic!("Expected QueryParticipantsProgress variant"),
+        }
+    };
+    let account_progress = |account: u64| -> Option<ParticipantProgress> {
+        match program
+            .read_state(StateQuery::QueryParticipantProgress {
+                id: 0,
+                account: account.into(),
+            })
+            .unwrap()
+        {
+            StateReply::QueryParticipantProgress { progress } => progress,
+            _ => panic!("Expected QueryParticipantProgress variant"),
+        }
+    };
+    let complete = |users: &[u64]| {
+        for &user in users {
+            program.send(user, Command::CompleteDaily { id: 0 });
+        }
+        system.run_next_block();
+    };
+
+    assert!(matches!(
+        analytics(1),
+        Err(Error::ChallengeNotFound { id: 1 })
+    ));
+
+    // nobody can have missed a day before the start
+    let before_start = analytics(0).unwrap();
+    assert_eq!(before_start.daily_completions, vec![0; 3]);
+    assert_eq!(before_start.participant_count, 4);
+    assert_eq!(before_start.alive_count, 4);
+    assert_eq!(before_start.alive_bps, 10000);
+    assert_eq!(before_start.projected_prize_per_stake_bps, 10000);
+    assert!(progress(0, 10)
+        .iter()
+        .all(|progress| progress.alive && progress.projected_prize == 30));
+
+    // the first day is not over until the second one starts
+    let start_time = query_challenge(&program, 0).start_time;
+    run_until(&system, start_time);
+    complete(&users[..3]);
+    let first_day = analytics(0).unwrap();
+    assert_eq!(first_day.daily_completions, vec![3, 0, 0]);
+    assert_eq!(first_day.alive_count, 4);
+    assert_eq!(account_progress(100).unwrap().current_streak, 1);
+    assert_eq!(account_progress(103).unwrap().current_streak, 0);
+
+    run_until(&system, start_time + 86400);
+    complete(&users[..2]);
+    let second_day = analytics(0).unwrap();
+    assert_eq!(second_day.daily_completions, vec![3, 2, 0]);
+    assert_eq!(second_day.alive_count, 3);
+    assert_eq!(second_day.alive_bps, 7500);
+    assert_eq!(second_day.projected_prize_per_stake_bps, 13333);
+    let leader = account_progress(100).unwrap();
+    assert_eq!(leader.account, ActorId::from(100));
+    assert_eq!(leader.days_completed, 2);
+    assert_eq!(leader.current_streak, 2);
+    assert_eq!(leader.longest_streak, 2);
+    assert_eq!(leader.projected_prize, 40);
+    // yesterday's streak still counts while today's quota is not met
+    assert_eq!(account_progress(102).unwrap().current_streak, 1);
+    let dropped = account_progress(103).unwrap();
+    assert!(!dropped.alive);
+    assert_eq!(dropped.projected_prize, 0);
+
+    run_until(&system, start_time + 2 * 86400);
+    let third_day = analytics(0).unwrap();
+    assert_eq!(third_day.alive_count, 2);
+    assert_eq!(third_day.projected_prize_per_stake_bps, 20000);
+    let page = progress(1, 2);
+    assert_eq!(
+        page.iter()
+            .map(|progress| (progress.account, progress.alive, progress.projected_prize))
+            .collect::<Vec<_>>(),
+        vec![
+            (ActorId::from(101), true, 60),
+            (ActorId::from(102), false, 0)
+        ]
+    );
+    assert_eq!(page[1].current_streak, 0);
+    assert!(account_progress(USER).is_none());
+}
+
+#[test]
+fn test_analytics_of_a_large_token_pool() {
+    let system = setup_system();
+    let program = setup_program(&system);
+
+    // a stake that overflows a u128 once scaled to basis points
+    let stake = u128::MAX / 1000;
+    let balances = Rc::new(RefCell::new(BTreeMap::from([(ActorId::from(USER), stake)])));
+    let token = Program::mock_with_id(
+        &system,
+        TOKEN,
+        MockToken {
+            owner: program.id(),
+            balances,
+        },
+    );
+    token.send_bytes(USER, []);
+    system.run_next_block();
+
+    let mut command = challenge_command(&system, 1, 1);
+    if let Command::CreateChallenge {
+        entry_fee,
+        currency,
+        ..
+    } = &mut command
+    {
+        *entry_fee = stake;
+        *currency = Some(token.id());
+    }
+    submit_challenge(&system, &program, command);
+    program.send(
+        USER,
+        Command::JoinChallenge {
+            id: 0,
+            team: None,
+            timezone: None,
+        },
+    );
+    system.run_next_block();
+    system.run_next_block();
+    assert_eq!(query_challenge(&program, 0).prize_pool, stake);
+
+    let state_reply: StateReply = program
+        .read_state(StateQuery::QueryChallengeAnalytics { id: 0 })
+        .unwrap();
+    let StateReply::QueryChallengeAnalytics { analytics } = state_reply else {
+        panic!("Expected QueryChallengeAnalytics variant");
+    };
+    assert_eq!(analytics.projected_prize_per_stake_bps, 10000);
+}
+
+#[test]
+fn test_simulate_payout() {
+    let system = setup_system();
+    let program = setup_program(&system);
+    let mut command = challenge_command(&system, 1, 3);
+    if let Command::CreateChallenge {
+        entry_fee,
+        late_join,
+        ..
+    } = &mut command
+    {
+        *entry_fee = 30;
+        *late_join = Some(LateJoin {
+            days: 2,
+            premium: 0,
+        });
+    }
+    submit_challenge(&system, &program, command);
+
+    let (first, second, joiner) = (100, 101, 102);
+    for user in [first, second, joiner] {
+        system.mint_to(user, constants::EXISTENTIAL_DEPOSIT * 10);
+    }
+    let join = |user| {
+        program.send_with_value(
+            user,
+            Command::JoinChallenge {
+                id: 0,
+                team: None,
+                timezone: None,
+            },
+            30,
+        );
+        system.run_next_block();
+    };
+    join(first);
+    join(second);
+
+    let simulate = |account: u64, stake, completion| -> Result<PayoutSimulation, Error> {
+        match program
+            .read_state(StateQuery::SimulatePayout {
+                id: 0,
+                account: account.into(),
+                team: None,
+                hypothetical_stake: stake,
+                assumed_completion: completion,
+            })
+            .unwrap()
+        {
+            StateReply::SimulatePayout { simulation } => Ok(simulation),
+            StateReply::Error { error } => Err(error),
+            _ => panic!("Expected SimulatePayout variant"),
+        }
+    };
+    let scenarios = |simulation: PayoutSimulation| {
+        (
+            simulation.everyone_finishes,
+            simulation.survivors_finish,
+            simulation.only_account_finishes,
+        )
+    };
+
+    assert_eq!(
+        scenarios(simulate(joiner, 30, 10000).unwrap()),
+        (30, 30, 90)
+    );
+    // a stake above the entry fee weighs more
+    assert_eq!(
+        scenarios(simulate(joiner, 60, 10000).unwrap()),
+        (60, 60, 120)
+    );
+    // nobody wins anything without finishing
+    assert_eq!(scenarios(simulate(joiner, 30, 9999).unwrap()), (0, 0, 0));
+    assert!(matches!(
+        simulate(joiner, 20, 10000),
+        Err(Error::NotEnoughFunds {
+            expected: 30,
+            actual: 20
+        })
+    ));
+    assert!(matches!(
+        simulate(first, 30, 10000),
+        Err(Error::ParticipantAlreadyJoined { id: 0 })
+    ));
+
+    // the second participant misses the first day, and a late joiner's stake
+    // weighs for the single day left to them
+    let challenge = query_challenge(&program, 0);
+    run_until(&system, challenge.start_time);
+    program.send(first, Command::CompleteDaily { id: 0 });
+    system.run_next_block();
+    run_until(&system, challenge.start_time + 86400 + 3600);
+    let simulation = simulate(joiner, 30, 10000).unwrap();
+    assert_eq!(scenarios(simulation.clone()), (12, 22, 90));
+
+    let challenge = query_challenge(&program, 0);
+    assert_eq!(challenge.participant_count, 2);
+    assert_eq!(challenge.prize_pool, 60);
+
+    // the survivors do finish, and the late joiner wins what was simulated
+    join(joiner);
+    program.send(first, Command::CompleteDaily { id: 0 });
+    system.run_next_block();
+    run_until(&system, challenge.start_time + 2 * 86400);
+    program.send(first, Command::CompleteDaily { id: 0 });
+    program.send(joiner, Command::CompleteDaily { id: 0 });
+    system.run_next_block();
+    run_until(&system, challenge.end_time);
+    system.run_next_block();
+
+    let state_reply: StateReply = program
+        .read_state(StateQuery::QueryProfile {
+            account: joiner.into(),
+        })
+        .unwrap();
+    let StateReply::QueryProfile {
+        profile: Some(profile),
+    } = state_reply
+    else {
+        panic!("Expected a profile");
+    };
+    assert_eq!(profile.lifetime_won, simulation.survivors_finish);
+}
+
+#[test]
+fn test_challenge_log() {
+    let system = setup_system();
+    let program = setup_program(&system);
+    let (participant, sponsor) = (100, 101);
+    for user in [participant, sponsor] {
+        system.mint_to(user, constants::EXISTENTIAL_DEPOSIT * 10);
+    }
+
+    let log = |offset, count| -> Result<(Vec<LogEntry>, u64), Error> {
+        match program
+            .read_state(StateQuery::QueryChallengeLog {
+                id: 0,
+                offset,
+                count,
+            })
+            .unwrap()
+        {
+            StateReply::QueryChallengeLog { entries, total } => Ok((entries, total)),
+            StateReply::Error { error } => Err(error),
+            _ => panic!("Expected QueryChallengeLog variant"),
+        }
+    };
+    let entries = || log(0, 100).unwrap().0;
+    assert!(matches!(
+        log(0, 10),
+        Err(Error::ChallengeNotFound { id: 0 })
+    ));
+
+    // every entry is broadcast to the zero address as it is made
+    program.send(USER, challenge_command(&system, 1, 1));
+    let block_run_result = system.run_next_block();
+    let created = entries()[0].clone();
+    assert_eq!(created.block, system.block_height());
+    assert!(
+        block_run_result.contains(&Log::builder().dest(0).payload(Event::ChallengeLogged {
+            id: 0,
+            entry: created,
+        }))
+    );
+
+    program.send_with_value(
+        participant,
+        Command::JoinChallenge {
+            id: 0,
+            team: None,
+            timezone: None,
+        },
+        1,
+    );
+    program.send_with_value(sponsor, Command::SponsorChallenge { id: 0, amount: 0 }, 2);
+    system.run_next_block();
+
+    // the day is logged once however often it is checked in
+    let challenge = query_challenge(&program, 0);
+    run_until(&system, challenge.start_time);
+    for _ in 0..2 {
+        program.send(participant, Command::CompleteDaily { id: 0 });
+        system.run_next_block();
+    }
+    run_until(&system, challenge.end_time);
+    system.run_next_block();
+
+    let program_id = ActorId::from(program.id().into_bytes());
+    let entries = entries();
+    assert_eq!(
+        entries
+            .iter()
+            .map(|entry| (entry.actor, entry.action, entry.amount))
+            .collect::<Vec<_>>(),
+        vec![
+            (ActorId::from(USER), LogAction::Created, 0),
+            (ActorId::from(participant), LogAction::Joined, 1),
+            (ActorId::from(sponsor), LogAction::Sponsored, 2),
+            (
+                program_id,
+                LogAction::RecruitmentEnded {
+                    status: ChallengeStatus::Executing
+                },
+                0
+            ),
+            (
+                ActorId::from(participant),
+                LogAction::DayCompleted { day: 0 },
+                0
+            ),
+            (ActorId::from(participant), LogAction::PrizeSent, 3),
+            (program_id, LogAction::Completed, 3),
+        ]
+    );
+    assert!(entries
+        .windows(2)
+        .all(|pair| pair[0].block <= pair[1].block && pair[0].timestamp <= pair[1].timestamp));
+
+    let (page, total) = log(5, 10).unwrap();
+    assert_eq!(total, 7);
+    assert_eq!(page.len(), 2);
+    assert_eq!(page[0].action, LogAction::PrizeSent);
+}
+
+#[test]
+fn test_global_stats() {
+    let system = setup_system();
+    let program = setup_program(&system);
+    let stats = || -> GlobalStats {
+        match program.read_state(StateQuery::QueryGlobalStats).unwrap() {
+            StateReply::QueryGlobalStats { stats } => stats,
+            _ => panic!("Expected QueryGlobalStats variant"),
+        }
+    };
+    let native = |stats: &GlobalStats| -> CurrencyStats {
+        assert_eq!(stats.currencies.len(), 1);
+        assert_eq!(stats.currencies[0].currency, None);
+        stats.currencies[0].clone()
+    };
+
+    let initial = stats();
+    assert!(initial.currencies.is_empty());
+    assert_eq!(initial.challenge_count, 0);
+    assert_eq!(initial.unaccounted_value, initial.value_available as i128);
+
+    // the first challenge runs, and nobody joins the second one
+    let mut command = challenge_command(&system, 1, 1);
+    if let Command::CreateChallenge { entry_fee, .. } = &mut command {
+        *entry_fee = 10;
+    }
+    submit_challenge(&system, &program, command.clone());
+    submit_challenge(&system, &program, command);
+    let (first, second, sponsor) = (100, 101, 102);
+    for user in [first, second, sponsor] {
+        system.mint_to(user, constants::EXISTENTIAL_DEPOSIT * 10);
+    }
+    for user in [first, second] {
+        program.send_with_value(
+            user,
+            Command::JoinChallenge {
+                id: 0,
+                team: None,
+                timezone: None,
+            },
+            10,
+        );
+    }
+    for id in [0, 1] {
+        program.send_with_value(sponsor, Command::SponsorChallenge { id, amount: 0 }, 10);
+    }
+    system.run_next_block();
+
+    let recruiting = stats();
+    assert_eq!(recruiting.challenge_count, 2);
+    assert_eq!(recruiting.recruiting, 2);
+    assert_eq!(recruiting.unique_participants, 2);
+    let totals = native(&recruiting);
+    assert_eq!(
+        (totals.staked, totals.sponsored, totals.escrow),
+        (20, 20, 40)
+    );
+    assert_eq!(
+        recruiting.value_available,
+        initial.value_available + totals.escrow
+    );
+    assert_eq!(recruiting.unaccounted_value, initial.unaccounted_value);
+
+    let challenge = query_challenge(&program, 0);
+    run_until(&system, challenge.start_time);
+    // checking in again on a completed day doesn't count it twice
+    for _ in 0..2 {
+        program.send(first, Command::CompleteDaily { id: 0 });
+        system.run_next_block();
+    }
+    let executing = stats();
+    assert_eq!((executing.executing, executing.recruit_failed), (1, 1));
+    assert_eq!(executing.completed_days, 1);
+    assert_eq!(native(&executing).refunded, 10);
+
+    run_until(&system, challenge.end_time);
+    system.run_next_block();
+    let completed = stats();
+    assert_eq!((completed.completed, completed.recruit_failed), (1, 1));
+    let totals = native(&completed);
+    // the sponsorship of the challenge that ran is part of its prize
+    assert_eq!(totals.paid_out, 30);
+    assert_eq!(totals.claimed, 0);
+    assert_eq!(totals.escrow, 0);
+    assert_eq!(completed.unaccounted_value, initial.unaccounted_value);
+}
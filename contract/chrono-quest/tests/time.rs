@@ -0,0 +1,148 @@
+use chrono_quest::time::{
+    calculate_future_day_start, day_start_at_or_after, is_start_of_day, is_valid_timezone, weekday,
+    Schedule,
+};
+use chrono_quest_io::Cadence;
+
+// 2024-01-01 00:00:00 UTC, a Monday
+const MONDAY: u64 = 1704067200;
+const DAY: u64 = 86400;
+
+#[test]
+fn test_sub_hour_timezones() {
+    assert!(is_valid_timezone(330));
+    assert!(is_valid_timezone(345));
+    assert!(is_valid_timezone(-720));
+    assert!(is_valid_timezone(840));
+    assert!(!is_valid_timezone(850));
+    assert!(!is_valid_timezone(-735));
+    assert!(!is_valid_timezone(310));
+
+    // local midnight in India is 18:30 UTC the day before
+    let india_midnight = MONDAY - 330 * 60;
+    assert!(is_start_of_day(india_midnight, 330));
+    assert!(!is_start_of_day(MONDAY, 330));
+    assert_eq!(
+        calculate_future_day_start(MONDAY, 330, 1),
+        india_midnight + DAY
+    );
+
+    let nepal_midnight = MONDAY - 345 * 60;
+    assert_eq!(
+        calculate_future_day_start(MONDAY, 345, 2),
+        nepal_midnight + 2 * DAY
+    );
+
+    let kiribati_midnight = MONDAY - 14 * 3600;
+    assert!(is_start_of_day(kiribati_midnight, 840));
+    assert_eq!(
+        calculate_future_day_start(MONDAY, 840, 1),
+        kiribati_midnight + DAY
+    );
+}
+
+#[test]
+fn test_day_start_at_or_after() {
+    assert_eq!(day_start_at_or_after(MONDAY, 0), MONDAY);
+    assert_eq!(day_start_at_or_after(MONDAY + 1, 0), MONDAY + DAY);
+    // midnight in UTC+8 is 4pm in UTC, 8 hours before midnight
+    let start = MONDAY - 8 * 3600;
+    assert_eq!(day_start_at_or_after(start, 0), MONDAY);
+    assert_eq!(day_start_at_or_after(start, -240), MONDAY + 4 * 3600);
+    assert_eq!(day_start_at_or_after(start, 330), MONDAY - 330 * 60);
+}
+
+#[test]
+fn test_weekday() {
+    assert_eq!(weekday(0, 0), 3);
+    assert_eq!(weekday(MONDAY, 0), 0);
+    assert_eq!(weekday(MONDAY - 1, 0), 6);
+    assert_eq!(weekday(MONDAY - 8 * 3600, 480), 0);
+    assert_eq!(weekday(MONDAY + 5 * 3600, -360), 6);
+}
+
+#[test]
+fn test_daily_schedule() {
+    let schedule = Schedule::new(Cadence::Daily, MONDAY, MONDAY + 5 * DAY, 0);
+    assert_eq!(schedule.execution_days(), 5);
+    assert_eq!(schedule.required_check_ins(), 5);
+    assert_eq!(schedule.counted_check_ins([0, 1, 2]), 3);
+    assert_eq!(schedule.counted_check_ins([0, 1, 2, 3, 4, 9]), 5);
+    assert!(schedule.accepts_check_in(4));
+    assert!(!schedule.accepts_check_in(5));
+}
+
+#[test]
+fn test_times_per_week_schedule() {
+    let schedule = Schedule::new(Cadence::TimesPerWeek(3), MONDAY, MONDAY + 10 * DAY, 0);
+    // three check-ins in the first week and all three days of the short second one
+    assert_eq!(schedule.required_check_ins(), 6);
+    assert_eq!(schedule.counted_check_ins([0, 1, 2, 3, 7]), 4);
+    assert_eq!(schedule.counted_check_ins([2, 4, 6, 7, 8, 9]), 6);
+    assert!(schedule.accepts_check_in(9));
+
+    let schedule = Schedule::new(Cadence::TimesPerWeek(7), MONDAY, MONDAY + 14 * DAY, 0);
+    assert_eq!(schedule.required_check_ins(), 14);
+}
+
+#[test]
+fn test_weekdays_schedule() {
+    let schedule = Schedule::new(Cadence::Weekdays, MONDAY, MONDAY + 14 * DAY, 0);
+    assert_eq!(schedule.required_check_ins(), 10);
+    assert!(schedule.accepts_check_in(4));
+    assert!(!schedule.accepts_check_in(5));
+    assert!(!schedule.accepts_check_in(6));
+    assert!(schedule.accepts_check_in(7));
+    assert_eq!(schedule.counted_check_ins([0, 1, 2, 3, 4, 5, 6]), 5);
+
+    // a weekend alone has nothing to check in on
+    let saturday = MONDAY + 5 * DAY;
+    let schedule = Schedule::new(Cadence::Weekdays, saturday, saturday + 2 * DAY, 0);
+    assert_eq!(schedule.required_check_ins(), 0);
+
+    // days follow the challenge timezone, where this start is a Monday
+    let start = MONDAY - 8 * 3600;
+    let schedule = Schedule::new(Cadence::Weekdays, start, start + DAY, 480);
+    assert!(schedule.accepts_check_in(0));
+
+    // a schedule starting on a Friday rests over the next two days
+    let schedule = Schedule::starting_on(Cadence::Weekdays, 4, 4);
+    assert_eq!(schedule.required_check_ins(), 2);
+    assert!(schedule.accepts_check_in(0));
+    assert!(!schedule.accepts_check_in(1));
+    assert!(!schedule.accepts_check_in(2));
+    assert!(schedule.accepts_check_in(3));
+}
+
+#[test]
+fn test_every_k_days_schedule() {
+    let schedule = Schedule::new(Cadence::EveryKDays(3), MONDAY, MONDAY + 7 * DAY, 0);
+    assert_eq!(schedule.required_check_ins(), 3);
+    assert_eq!(schedule.counted_check_ins([0, 1, 6]), 2);
+    assert_eq!(schedule.counted_check_ins([2, 3, 6]), 3);
+    assert!(schedule.accepts_check_in(1));
+    assert!(!schedule.accepts_check_in(7));
+}
+
+#[test]
+fn test_missed_check_ins() {
+    let schedule = Schedule::new(Cadence::Daily, MONDAY, MONDAY + 5 * DAY, 0);
+    assert_eq!(schedule.missed_check_ins([], 0), 0);
+    // today is not over yet, so its check-in is not missed
+    assert_eq!(schedule.missed_check_ins([0, 1], 2), 0);
+    assert_eq!(schedule.missed_check_ins([0, 2], 3), 1);
+    assert_eq!(schedule.missed_check_ins([0, 2], 9), 3);
+    assert_eq!(schedule.missed_check_ins([0, 1, 2, 3, 4], 5), 0);
+
+    // three check-ins a week can wait until the last three days of the week
+    let schedule = Schedule::new(Cadence::TimesPerWeek(3), MONDAY, MONDAY + 14 * DAY, 0);
+    assert_eq!(schedule.missed_check_ins([], 4), 0);
+    assert_eq!(schedule.missed_check_ins([], 5), 1);
+    assert_eq!(schedule.missed_check_ins([1], 6), 1);
+    assert_eq!(schedule.missed_check_ins([1, 2, 3], 10), 0);
+    assert_eq!(schedule.missed_check_ins([1, 2, 3], 14), 3);
+
+    let schedule = Schedule::new(Cadence::Weekdays, MONDAY, MONDAY + 14 * DAY, 0);
+    assert_eq!(schedule.missed_check_ins([0, 1, 2, 3, 4], 7), 0);
+    assert_eq!(schedule.missed_check_ins([0, 1, 2, 3], 7), 1);
+}
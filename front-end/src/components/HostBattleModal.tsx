@@ -64,8 +64,7 @@ export const HostBattleModal = ({ open, onClose }: Props) => {
     setIsSubmitting(true);
     setError('');
 
-    const timeZoneOffset = new Date().getTimezoneOffset();
-    const timezone = -(timeZoneOffset / 60);
+    const timezone = { Minutes: -new Date().getTimezoneOffset() };
     const startTime = startDate.valueOf() / 1000;
     const endTime = endDate.add(1, 'day').valueOf() / 1000;
 
@@ -82,6 +81,17 @@ export const HostBattleModal = ({ open, onClose }: Props) => {
           timezone,
           startTime,
           endTime,
+          cadence: 'Daily',
+          tasksPerDay: 1,
+          window: null,
+          lateJoin: null,
+          currency: null,
+          requirements: {
+            minCompletedBattles: null,
+            minCompletionRate: null,
+            requiredBadge: null,
+          },
+          teams: [],
         },
       },
       gasLimit: 750000000000,
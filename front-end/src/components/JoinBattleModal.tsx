@@ -42,6 +42,8 @@ export default function JoinBattleModal({ open, onClose, onJoinSucceed, battleId
       payload: {
         JoinChallenge: {
           id: battleId,
+          team: null,
+          timezone: null,
         },
       },
       gasLimit: 750000000000,